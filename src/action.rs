@@ -1,31 +1,169 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::builtin;
+use crate::config::{Action as ActionConfig, ActionRef};
+use crate::context::Context;
+use crate::interpolate;
 use crate::plugin::Plugin;
+use crate::util;
 
 pub struct Action {
-    plugin: Plugin,
+    kind: Kind,
+    env: HashMap<String, String>,
+}
+
+enum Kind {
+    Plugin(Plugin),
+    Template {
+        template: String,
+        args: serde_json::Map<String, serde_json::Value>,
+    },
 }
 
 impl Action {
-    pub fn new(name: &str) -> Self {
-        // Try local path first (config dir, then _PAL_CONFIG_DIR), fall back to github
-        let config_dir = dirs::config_dir()
-            .map(|p| p.join("pal"))
+    pub fn new(action: &ActionRef, actions: &HashMap<String, ActionConfig>) -> Result<Self, String> {
+        let name = action.name();
+        let def = actions.get(name);
+
+        // Config from the [action.<name>] table, overridden by per-use args
+        let mut args: serde_json::Map<String, serde_json::Value> = def
+            .map(|d| d.extra.clone().into_iter().collect())
             .unwrap_or_default();
-        let local_path = config_dir.join(format!("plugins/actions/{name}"));
+        args.extend(action.args());
 
-        let base = if local_path.join("plugin.toml").exists() {
-            local_path.to_string_lossy().to_string()
-        } else {
-            format!("github:zcag/pal/plugins/actions/{name}")
+        let env = def.map(|d| d.env.clone()).unwrap_or_default();
+
+        if let Some(template) = def.and_then(|d| d.template.clone()) {
+            return Ok(Self { kind: Kind::Template { template, args }, env });
+        }
+
+        let base = plugin_base(name, actions)?;
+        Ok(Self { kind: Kind::Plugin(Plugin::new(&base, &args)), env })
+    }
+
+    /// Run the action on a value in `ctx` (e.g. with the picked item's fields),
+    /// adding the action's own `env` table
+    pub fn run(&self, value: &str, ctx: &Context) -> Result<String, String> {
+        let mut ctx = ctx.clone();
+        for (k, v) in &self.env {
+            ctx.env.push((k.clone(), interpolate::resolve(v, &ctx.session)?));
+        }
+        match &self.kind {
+            Kind::Plugin(plugin) => plugin.try_run("run", Some(value), ctx),
+            Kind::Template { template, args } => {
+                let args = args
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), interpolate::resolve_json(v, &ctx.session)?)))
+                    .collect::<Result<_, String>>()?;
+                let script = render_template(template, value, &args);
+                util::run_command_checked(Path::new("bash"), &["-c", &script], None, &ctx)
+            }
+        }
+    }
+}
+
+/// Plugin base an action runs. `base` is either a plugin path or the name of another action:
+/// a configured one, a plugin in `plugins/actions/` or a builtin. Only actions without a
+/// `base` fall back to fetching from `FALLBACK_ACTIONS`.
+pub fn plugin_base(name: &str, actions: &HashMap<String, ActionConfig>) -> Result<String, String> {
+    let mut seen = vec![name];
+    let mut current = name;
+    loop {
+        let Some(base) = actions.get(current).and_then(|d| d.base.as_deref()) else {
+            return Ok(resolve_base(current));
         };
+        if base.contains('/') || base.contains(':') {
+            return Ok(base.to_string());
+        }
+        let cycle = seen.contains(&base);
+        seen.push(base);
+        if cycle {
+            return Err(format!("base cycle {}", seen.join(" -> ")));
+        }
+        match actions.get(base) {
+            Some(def) if def.template.is_some() => {
+                return Err(format!("base '{base}' in [action.{current}] is a template action, not a plugin"));
+            }
+            Some(_) => current = base,
+            None => {
+                return local_base(base).ok_or_else(|| format!("unknown base '{base}' in [action.{current}]"));
+            }
+        }
+    }
+}
 
-        // no user config for actions, pass empty object
-        let empty: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-        Self {
-            plugin: Plugin::new(&base, &empty),
+/// Run actions in sequence, feeding each action's stdout to the next one's stdin.
+/// An action with no output passes its input along; a failing action stops the chain.
+pub fn run_chain(chain: &[ActionRef], actions: &HashMap<String, ActionConfig>, value: &str, ctx: &Context) -> Result<String, String> {
+    let mut value = value.to_string();
+    let mut output = String::new();
+    for action in chain {
+        output = Action::new(action, actions)
+            .and_then(|a| a.run(&value, ctx))
+            .map_err(|e| format!("{}: {e}", action.name()))?;
+        if !output.trim().is_empty() {
+            value = output.trim_end().to_string();
         }
     }
+    Ok(output)
+}
 
-    pub fn run(&self, value: &str) -> String {
-        self.plugin.run("run", Some(value))
+/// Find the plugin base for an action name, falling back to github
+fn resolve_base(name: &str) -> String {
+    local_base(name).unwrap_or_else(|| format!("{FALLBACK_ACTIONS}{name}"))
+}
+
+/// An installed action plugin of that name, then a builtin
+fn local_base(name: &str) -> Option<String> {
+    let config_dir = dirs::config_dir()
+        .map(|p| p.join("pal"))
+        .unwrap_or_default();
+    let local_path = config_dir.join(format!("plugins/actions/{name}"));
+
+    if local_path.join("plugin.toml").exists() {
+        Some(local_path.to_string_lossy().to_string())
+    } else if builtin::actions::NAMES.contains(&name) {
+        Some(format!("builtin/actions/{name}"))
+    } else {
+        None
     }
 }
+
+/// Where actions that aren't builtin or configured are fetched from
+pub const FALLBACK_ACTIONS: &str = "github:zcag/pal/plugins/actions/";
+
+/// Substitute `{{value}}` and `{{<arg>}}` in a shell template, shell-quoting each value.
+/// One pass over the template, so placeholders inside substituted values are left alone.
+fn render_template(template: &str, value: &str, args: &serde_json::Map<String, serde_json::Value>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let replacement = if name == "value" {
+            Some(value.to_string())
+        } else {
+            args.get(name).map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+        };
+        match replacement {
+            Some(v) => out.push_str(&shell_quote(&v)),
+            None => out.push_str(&rest[start..start + len + 4]),
+        }
+        rest = &rest[start + len + 4..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Quote a string for `bash -c`
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
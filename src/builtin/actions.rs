@@ -0,0 +1,89 @@
+use std::process::{Command, Stdio};
+
+use crate::context::Context;
+
+use super::desktop;
+
+pub fn run(name: &str, cmd: &str, input: Option<&str>, ctx: &Context) -> String {
+    match cmd {
+        "run" => run_action(name, input.unwrap_or(""), ctx).unwrap_or_else(|e| {
+            eprintln!("{name}: {e}");
+            String::new()
+        }),
+        _ => {
+            eprintln!("{name}: unknown command: {cmd}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Names of actions implemented as builtins
+pub const NAMES: &[&str] = &["cmd", "copy", "open", "notify", "term"];
+
+/// Run a builtin action; the commands it starts run in `ctx`
+pub fn run_action(name: &str, value: &str, ctx: &Context) -> Result<String, String> {
+    if NAMES.contains(&name) && desktop::dry_run(name, &format!("run on {value:?}"), ctx) {
+        return Ok(String::new());
+    }
+    match name {
+        "cmd" => cmd(value, ctx),
+        "copy" => copy(value, &ctx.config),
+        "open" => open(value),
+        "notify" => notify(value, &ctx.config),
+        "term" => term(value, ctx),
+        _ => Err(format!("unknown action: {name}")),
+    }
+}
+
+/// Execute the value as a shell command, returning its stdout.
+/// With `terminal = true` the command runs interactively instead.
+fn cmd(value: &str, ctx: &Context) -> Result<String, String> {
+    if ctx.config.get("terminal").and_then(|v| v.as_bool()).unwrap_or(false) {
+        desktop::run_in_terminal(value, ctx)?;
+        return Ok(String::new());
+    }
+
+    let output = ctx.apply(&mut Command::new("bash"))
+        .args(["-c", value])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("failed to run bash: {e}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        Err(format!("command exited with {}", output.status.code().unwrap_or(-1)))
+    }
+}
+
+fn copy(value: &str, cfg: &serde_json::Value) -> Result<String, String> {
+    let primary = cfg.get("primary").and_then(|v| v.as_bool()).unwrap_or(false);
+    if !desktop::copy(value, primary) {
+        return Err("no clipboard tool found (wl-copy, xclip, pbcopy)".into());
+    }
+    desktop::notify("Copied", value);
+    Ok(String::new())
+}
+
+fn open(value: &str) -> Result<String, String> {
+    if desktop::open(value) {
+        Ok(String::new())
+    } else {
+        Err("no opener found (xdg-open, open)".into())
+    }
+}
+
+fn notify(value: &str, cfg: &serde_json::Value) -> Result<String, String> {
+    let title = cfg.get("title").and_then(|v| v.as_str()).unwrap_or("pal");
+    desktop::notify(title, value);
+    Ok(String::new())
+}
+
+/// Run the value as a shell command in a terminal, inline on TTY frontends
+fn term(value: &str, ctx: &Context) -> Result<String, String> {
+    desktop::run_in_terminal(value, ctx)?;
+    Ok(String::new())
+}
@@ -5,12 +5,16 @@ use std::process::Command;
 
 use serde_json::json;
 
+use crate::context::Context;
+
+use super::desktop;
 use super::file_util::{scan_dirs, ScanOptions};
+use super::launch::{self, LaunchOptions};
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
         "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "pick" => pick(input.unwrap_or(""), ctx),
         _ => {
             eprintln!("apps: unknown command: {cmd}");
             std::process::exit(1);
@@ -32,7 +36,7 @@ fn list() -> String {
         ..Default::default()
     };
 
-    let files = scan_dirs(&dirs.iter().map(|s| *s).collect::<Vec<_>>(), &opts);
+    let files = scan_dirs(&dirs, &opts);
 
     let mut apps: HashMap<String, serde_json::Value> = HashMap::new();
 
@@ -68,6 +72,7 @@ fn parse_desktop_file(path: &Path) -> Option<serde_json::Value> {
     let mut no_display = false;
     let mut hidden = false;
     let mut is_app = false;
+    let mut terminal = false;
 
     for line in content.lines() {
         let line = line.trim();
@@ -86,6 +91,7 @@ fn parse_desktop_file(path: &Path) -> Option<serde_json::Value> {
                 "Type" => is_app = value == "Application",
                 "NoDisplay" => no_display = value == "true",
                 "Hidden" => hidden = value == "true",
+                "Terminal" => terminal = value == "true",
                 _ => {}
             }
         }
@@ -100,10 +106,11 @@ fn parse_desktop_file(path: &Path) -> Option<serde_json::Value> {
         "name": name.unwrap(),
         "exec": exec.unwrap(),
         "icon": icon.unwrap_or_default(),
+        "terminal": terminal,
     }))
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, ctx: &Context) -> String {
     let item: serde_json::Value = serde_json::from_str(input).unwrap_or_default();
     let exec = item.get("exec").and_then(|v| v.as_str()).unwrap_or("");
 
@@ -118,10 +125,26 @@ fn pick(input: &str) -> String {
         .collect::<Vec<_>>()
         .join(" ");
 
-    let _ = Command::new("sh")
-        .arg("-c")
-        .arg(&exec)
-        .spawn();
+    if desktop::dry_run("apps", &format!("run {exec}"), ctx) {
+        return String::new();
+    }
+    if item.get("terminal").and_then(|v| v.as_bool()).unwrap_or(false) {
+        if let Err(e) = desktop::run_in_terminal(&exec, ctx) {
+            eprintln!("apps: {e}");
+        }
+        return String::new();
+    }
+
+    let app_id = item.get("id").and_then(|v| v.as_str())
+        .and_then(|id| Path::new(id).file_stem())
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(&exec);
+    if let Err(e) = launch::detached(cmd, &app_id, &LaunchOptions::from_config(&ctx.config)) {
+        eprintln!("apps: {e}");
+    }
 
     String::new()
 }
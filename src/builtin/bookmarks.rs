@@ -3,10 +3,15 @@ use std::process::Command;
 
 use serde_json::json;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use crate::context::Context;
+
+use super::desktop;
+use super::launch::{self, LaunchOptions};
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
-        "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "list" => list(&ctx.config),
+        "pick" => pick(input.unwrap_or(""), ctx),
         _ => {
             eprintln!("bookmarks: unknown command: {cmd}");
             std::process::exit(1);
@@ -14,13 +19,7 @@ pub fn run(cmd: &str, input: Option<&str>) -> String {
     }
 }
 
-fn config() -> serde_json::Value {
-    let s = std::env::var("_PAL_PLUGIN_CONFIG").unwrap_or_default();
-    serde_json::from_str(&s).unwrap_or_default()
-}
-
-fn list() -> String {
-    let cfg = config();
+fn list(cfg: &serde_json::Value) -> String {
     let browser = cfg.get("browser")
         .and_then(|v| v.as_str())
         .unwrap_or("firefox");
@@ -165,15 +164,19 @@ fn extract_chrome_bookmarks(node: &serde_json::Value, results: &mut Vec<serde_js
     }
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, ctx: &Context) -> String {
     let item: serde_json::Value = serde_json::from_str(input).unwrap_or_default();
     let url = item.get("url").and_then(|v| v.as_str()).unwrap_or("");
 
-    if url.is_empty() {
+    if url.is_empty() || desktop::dry_run("bookmarks", &format!("open {url}"), ctx) {
         return String::new();
     }
 
-    let _ = Command::new("xdg-open").arg(url).spawn();
+    let mut cmd = Command::new("xdg-open");
+    cmd.arg(url);
+    if let Err(e) = launch::detached(cmd, "bookmarks", &LaunchOptions::from_config(&ctx.config)) {
+        eprintln!("bookmarks: {e}");
+    }
 
     String::new()
 }
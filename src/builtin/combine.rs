@@ -1,10 +1,13 @@
+use std::sync::Arc;
+
 use crate::config::Config;
+use crate::context::{Context, Session};
 use crate::palette::Palette;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
-        "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "list" => list(&ctx.config, &ctx.session),
+        "pick" => pick(input.unwrap_or(""), &ctx.session),
         _ => {
             eprintln!("combine: unknown command: {cmd}");
             std::process::exit(1);
@@ -12,24 +15,23 @@ pub fn run(cmd: &str, input: Option<&str>) -> String {
     }
 }
 
-fn config() -> serde_json::Value {
-    let s = std::env::var("_PAL_PLUGIN_CONFIG").unwrap_or_default();
-    serde_json::from_str(&s).unwrap_or_default()
-}
-
-fn pal_config() -> Config {
-    let path = std::env::var("_PAL_CONFIG").unwrap_or_else(|_| "pal.default.toml".into());
-    Config::load(&path, &crate::Cli::default()).unwrap()
+fn pal_config(session: &Session) -> Config {
+    let cli = crate::Cli { profile: session.profile.clone(), ..Default::default() };
+    Config::load(&session.config_file(), &cli).unwrap()
 }
 
-fn list() -> String {
-    let cfg = config();
+fn list(cfg: &serde_json::Value, session: &Arc<Session>) -> String {
     let include = cfg.get("include")
         .and_then(|v| v.as_array())
         .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect::<Vec<_>>())
         .unwrap_or_default();
 
-    let pal_cfg = pal_config();
+    let mut pal_cfg = pal_config(session);
+    for palette_name in &include {
+        if let Err(e) = pal_cfg.resolve_palette(palette_name) {
+            eprintln!("combine: {e}");
+        }
+    }
 
     include.iter()
         .flat_map(|palette_name| {
@@ -40,7 +42,7 @@ fn list() -> String {
             let fallback_icon = palette_cfg.icon.as_deref().unwrap_or("");
             let fallback_icon_xdg = palette_cfg.icon_xdg.as_deref().unwrap_or("");
             let fallback_icon_utf = palette_cfg.icon_utf.as_deref().unwrap_or("");
-            Palette::new(palette_cfg).list(None)
+            Palette::new(&pal_cfg, palette_cfg, session).list(None)
                 .lines()
                 .filter_map(|line| {
                     let mut item: serde_json::Value = serde_json::from_str(line).ok()?;
@@ -63,7 +65,7 @@ fn list() -> String {
         .join("\n")
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, session: &Arc<Session>) -> String {
     let item: serde_json::Value = serde_json::from_str(input).unwrap_or_default();
     let source = item.get("_source").and_then(|v| v.as_str()).unwrap_or("");
 
@@ -71,10 +73,14 @@ fn pick(input: &str) -> String {
         return String::new();
     }
 
-    let cfg = pal_config();
+    let mut cfg = pal_config(session);
+    if let Err(e) = cfg.resolve_palette(source) {
+        eprintln!("combine: {e}");
+        return String::new();
+    }
     let Some(palette_cfg) = cfg.palette.get(source) else {
         return String::new();
     };
 
-    Palette::new(palette_cfg).pick(input)
+    Palette::new(&cfg, palette_cfg, session).pick(input)
 }
@@ -0,0 +1,151 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::context::Context;
+
+use super::launch::{self, LaunchOptions};
+
+/// Check if an executable is available on PATH
+pub fn has_command(name: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else { return false };
+    std::env::split_paths(&path).any(|dir| dir.join(name).is_file())
+}
+
+/// Copy text to the clipboard (wl-copy, xclip or pbcopy). Returns false if none worked.
+/// `primary` targets the X11/Wayland primary selection instead.
+pub fn copy(text: &str, primary: bool) -> bool {
+    let cmd: &[&str] = if has_command("wl-copy") {
+        if primary { &["wl-copy", "--primary"] } else { &["wl-copy"] }
+    } else if has_command("xclip") {
+        &["xclip", "-selection", if primary { "primary" } else { "clipboard" }]
+    } else if has_command("pbcopy") {
+        &["pbcopy"]
+    } else {
+        return false;
+    };
+
+    Command::new(cmd[0])
+        .args(&cmd[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .and_then(|mut c| {
+            if let Some(mut stdin) = c.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            c.wait()
+        })
+        .is_ok_and(|s| s.success())
+}
+
+/// Show a desktop notification, truncating long bodies
+pub fn notify(title: &str, body: &str) {
+    if !has_command("notify-send") {
+        return;
+    }
+    let mut display: String = body.chars().take(50).collect();
+    if body.chars().count() > 50 {
+        display.push_str("...");
+    }
+    let _ = Command::new("notify-send")
+        .args(["-t", "2000", title, &display])
+        .spawn();
+}
+
+/// Open a URL or file with the default application
+pub fn open(target: &str) -> bool {
+    let opener = if has_command("xdg-open") {
+        "xdg-open"
+    } else if has_command("open") {
+        "open"
+    } else {
+        return false;
+    };
+    let mut cmd = Command::new(opener);
+    cmd.arg(target);
+    launch::detached(cmd, opener, &LaunchOptions::default()).is_ok()
+}
+
+/// Whether side effects are off (`_PAL_DRY_RUN=1`, set by `pal plugin test`).
+/// Reports what `plugin` would have done on stderr.
+pub fn dry_run(plugin: &str, what: &str, ctx: &Context) -> bool {
+    let dry = ctx.session.dry_run;
+    if dry {
+        eprintln!("{plugin}: dry run, would {what}");
+    }
+    dry
+}
+
+/// Whether picked items can run in the current terminal.
+/// Set by pal from the frontend (`_PAL_TTY`), otherwise checks stdin directly.
+pub fn has_tty(ctx: &Context) -> bool {
+    use std::io::IsTerminal;
+    ctx.session.tty.unwrap_or_else(|| std::io::stdin().is_terminal())
+}
+
+/// Run a shell command that needs a terminal: inline when pal owns one,
+/// otherwise in a new terminal emulator window. The command runs in `ctx`.
+pub fn run_in_terminal(cmd: &str, ctx: &Context) -> Result<(), String> {
+    if has_tty(ctx) {
+        let status = ctx.apply(&mut Command::new("bash"))
+            .args(["-c", cmd])
+            .status()
+            .map_err(|e| format!("failed to run bash: {e}"))?;
+        return if status.success() {
+            Ok(())
+        } else {
+            Err(format!("command exited with {}", status.code().unwrap_or(-1)))
+        };
+    }
+
+    let term = terminal(ctx).ok_or("no terminal emulator found, set general.terminal or $TERMINAL")?;
+    let mut command = Command::new(&term[0]);
+    // The window is a session of its own, so only the env files and the scoped env carry over,
+    // not `_PAL_TTY` and co.
+    command
+        .args(&term[1..])
+        .args(["bash", "-c", cmd])
+        .envs(ctx.session.env.iter().cloned())
+        .envs(ctx.env.iter().cloned());
+    if let Some(ref cwd) = ctx.cwd {
+        command.current_dir(cwd);
+    }
+    launch::detached(command, "terminal", &LaunchOptions::default())
+}
+
+/// Terminal emulator command prefix, ready to append a program and its args.
+/// Uses `general.terminal` (`_PAL_TERMINAL`) verbatim, then $TERMINAL,
+/// then the first known emulator on PATH.
+pub fn terminal(ctx: &Context) -> Option<Vec<String>> {
+    if let Some(ref term) = ctx.session.terminal {
+        let cmd: Vec<String> = term.split_whitespace().map(String::from).collect();
+        if !cmd.is_empty() {
+            return Some(cmd);
+        }
+    }
+
+    if let Ok(term) = std::env::var("TERMINAL") {
+        if !term.trim().is_empty() {
+            let name = term.split_whitespace().next().unwrap_or("");
+            let mut cmd: Vec<String> = term.split_whitespace().map(String::from).collect();
+            if cmd.len() == 1 {
+                cmd.extend(exec_flag(name).map(String::from));
+            }
+            return Some(cmd);
+        }
+    }
+
+    ["kitty", "alacritty", "foot", "wezterm", "ghostty", "gnome-terminal", "konsole", "xterm"]
+        .into_iter()
+        .find(|t| has_command(t))
+        .map(|t| std::iter::once(t).chain(exec_flag(t)).map(String::from).collect())
+}
+
+fn exec_flag(terminal: &str) -> Option<&'static str> {
+    match terminal.rsplit('/').next().unwrap_or(terminal) {
+        "kitty" | "foot" => None,
+        "wezterm" => Some("start"),
+        "gnome-terminal" => Some("--"),
+        _ => Some("-e"),
+    }
+}
@@ -1,5 +1,7 @@
 use std::path::{Path, PathBuf};
 
+use crate::util;
+
 pub struct ScanOptions<'a> {
     pub pattern: Option<&'a str>,
     pub extension: Option<&'a str>,
@@ -58,7 +60,8 @@ fn collect_files(dir: &Path, opts: &ScanOptions, depth: usize, results: &mut Vec
             .unwrap_or(true);
 
         let matches_pattern = opts.pattern
-            .map(|p| glob_match(name, p))
+            // Scan patterns ignore case
+            .map(|p| util::wildcard_match(&p.to_lowercase(), &name.to_lowercase()))
             .unwrap_or(true);
 
         let matches_type = match (opts.dirs_only, opts.files_only) {
@@ -77,46 +80,10 @@ fn collect_files(dir: &Path, opts: &ScanOptions, depth: usize, results: &mut Vec
     }
 }
 
-pub fn glob_match(name: &str, pattern: &str) -> bool {
-    if pattern == "*" {
-        return true;
-    }
-
-    let pattern_chars: Vec<char> = pattern.chars().collect();
-    let name_chars: Vec<char> = name.chars().collect();
-
-    glob_match_impl(&pattern_chars, &name_chars, 0, 0)
-}
-
-fn glob_match_impl(pattern: &[char], name: &[char], pi: usize, ni: usize) -> bool {
-    if pi == pattern.len() {
-        return ni == name.len();
-    }
-
-    match pattern[pi] {
-        '*' => {
-            for i in ni..=name.len() {
-                if glob_match_impl(pattern, name, pi + 1, i) {
-                    return true;
-                }
-            }
-            false
-        }
-        '?' => {
-            ni < name.len() && glob_match_impl(pattern, name, pi + 1, ni + 1)
-        }
-        c => {
-            ni < name.len()
-                && name[ni].to_lowercase().next() == c.to_lowercase().next()
-                && glob_match_impl(pattern, name, pi + 1, ni + 1)
-        }
-    }
-}
-
 pub fn expand_home(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
+    if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]);
+            return home.join(rest);
         }
     } else if path == "~" {
         if let Some(home) = dirs::home_dir() {
@@ -1,11 +1,13 @@
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::process::Stdio;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use crate::context::Context;
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
-        "run" => run_fzf(input.unwrap_or("")),
-        "prompt" => prompt(input.unwrap_or("Input")),
-        "input_run" => input_run(input.unwrap_or("Input")),
+        "run" => run_fzf(input.unwrap_or(""), ctx),
+        "prompt" => prompt(input.unwrap_or("Input"), ctx),
+        "input_run" => input_run(input.unwrap_or("Input"), ctx),
         _ => {
             eprintln!("fzf: unknown command: {cmd}");
             std::process::exit(1);
@@ -47,8 +49,8 @@ pub fn format_items(items: &str) -> String {
         .join("\n")
 }
 
-fn prompt(message: &str) -> String {
-    let child = Command::new("fzf")
+fn prompt(message: &str, ctx: &Context) -> String {
+    let child = ctx.command("fzf")
         .args([
             "--disabled", "--print-query",
             &format!("--prompt={message}> "),
@@ -77,9 +79,9 @@ fn prompt(message: &str) -> String {
 }
 
 /// Live input mode: fzf reloads items on each keystroke via `pal _input-list`
-fn input_run(message: &str) -> String {
-    let palette = std::env::var("_PAL_PALETTE").unwrap_or_default();
-    let config = std::env::var("_PAL_CONFIG").unwrap_or_default();
+fn input_run(message: &str, ctx: &Context) -> String {
+    let palette = ctx.session.palette.as_deref().unwrap_or_default();
+    let config = ctx.session.config_file();
     let exe = std::env::current_exe()
         .unwrap_or_else(|_| "pal".into())
         .to_string_lossy()
@@ -90,7 +92,7 @@ fn input_run(message: &str) -> String {
         exe, config, palette
     );
 
-    let mut child = Command::new("fzf")
+    let mut child = ctx.command("fzf")
         .args([
             "--disabled", "--ansi", "--no-sort", "--layout=reverse",
             "--delimiter=\t", "--with-nth=2",
@@ -126,8 +128,8 @@ fn input_run(message: &str) -> String {
         .to_string()
 }
 
-fn run_fzf(items: &str) -> String {
-    let mut child = Command::new("fzf")
+fn run_fzf(items: &str, ctx: &Context) -> String {
+    let mut child = ctx.command("fzf")
         .args([
             "--ansi", "--no-sort", "--layout=reverse",
             "--delimiter=\t", "--with-nth=2"
@@ -0,0 +1,108 @@
+use std::fs::OpenOptions;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use super::desktop;
+
+/// How a detached process is started
+#[derive(Default)]
+pub struct LaunchOptions {
+    /// Wrap in `systemd-run --user --scope` so the app gets its own cgroup
+    pub systemd: bool,
+}
+
+impl LaunchOptions {
+    /// Read options from the plugin config (`systemd_run = true`)
+    pub fn from_config(cfg: &serde_json::Value) -> Self {
+        Self {
+            systemd: cfg.get("systemd_run").and_then(|v| v.as_bool()).unwrap_or(false),
+        }
+    }
+}
+
+/// Launch a command fully detached from pal: double-forked into its own session,
+/// with pal's `PAL_*`/`_PAL_*` env removed and stdio redirected to a per-app log.
+/// Closing the terminal pal was started from won't take the app down with it.
+pub fn detached(cmd: Command, app_id: &str, opts: &LaunchOptions) -> Result<(), String> {
+    let id = sanitize_id(app_id);
+
+    let mut cmd = if opts.systemd && desktop::has_command("systemd-run") {
+        let unit = format!("app-pal-{id}-{}", std::process::id());
+        let mut wrapped = Command::new("systemd-run");
+        wrapped
+            .args(["--user", "--scope", "--quiet", "--collect", "--unit", &unit, "--"])
+            .arg(cmd.get_program())
+            .args(cmd.get_args())
+            .envs(cmd.get_envs().filter_map(|(k, v)| Some((k, v?))));
+        wrapped
+    } else {
+        cmd
+    };
+
+    // Variables set on the command itself (e.g. item fields for a terminal command) stay
+    let explicit: Vec<_> = cmd.get_envs().filter(|(_, v)| v.is_some()).map(|(k, _)| k.to_os_string()).collect();
+    for (key, _) in std::env::vars_os() {
+        let key_str = key.to_string_lossy();
+        if (key_str.starts_with("PAL_") || key_str.starts_with("_PAL_")) && !explicit.contains(&key) {
+            cmd.env_remove(&key);
+        }
+    }
+
+    cmd.stdin(Stdio::null());
+    match open_log(&id) {
+        Some((out, err)) => { cmd.stdout(out).stderr(err); }
+        None => { cmd.stdout(Stdio::null()).stderr(Stdio::null()); }
+    }
+
+    // SAFETY: only async-signal-safe calls (fork, setsid, _exit) between fork and exec
+    unsafe {
+        cmd.pre_exec(|| {
+            match libc::fork() {
+                -1 => return Err(std::io::Error::last_os_error()),
+                0 => {}
+                // Intermediate child exits right away so the app is reparented to init
+                _ => libc::_exit(0),
+            }
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd.spawn().map_err(|e| format!("failed to launch {program}: {e}"))?;
+    // Reap the intermediate child
+    let _ = child.wait();
+    Ok(())
+}
+
+/// Open the log file for an app, returning handles for stdout and stderr
+fn open_log(id: &str) -> Option<(std::fs::File, std::fs::File)> {
+    let dir = log_dir();
+    std::fs::create_dir_all(&dir).ok()?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{id}.log")))
+        .ok()?;
+    let err = file.try_clone().ok()?;
+    Some((file, err))
+}
+
+fn log_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("pal/logs")
+}
+
+/// Make an id safe for unit and file names
+fn sanitize_id(id: &str) -> String {
+    let id: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    if id.is_empty() { "app".into() } else { id }
+}
@@ -1,27 +1,37 @@
+use crate::context::Context;
+
+pub mod actions;
 mod apps;
 mod bookmarks;
 mod combine;
+pub mod desktop;
 mod file_util;
 pub mod fzf;
+mod launch;
 mod pals;
 mod psg;
 pub mod rofi;
 mod ssh;
 mod stdin;
 
-pub fn run(base: &str, cmd: &str, input: Option<&str>) -> String {
+/// Run a builtin plugin command in `ctx`
+pub fn run(base: &str, cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     let path = base.strip_prefix("builtin/").unwrap_or(base);
     match path {
         // Palettes
-        "palettes/apps" => apps::run(cmd, input),
-        "palettes/bookmarks" => bookmarks::run(cmd, input),
-        "palettes/pals" => pals::run(cmd, input),
-        "palettes/psg" => psg::run(cmd, input),
-        "palettes/ssh" => ssh::run(cmd, input),
-        "palettes/combine" => combine::run(cmd, input),
+        "palettes/apps" => apps::run(cmd, input, ctx),
+        "palettes/bookmarks" => bookmarks::run(cmd, input, ctx),
+        "palettes/pals" => pals::run(cmd, input, ctx),
+        "palettes/psg" => psg::run(cmd, input, ctx),
+        "palettes/ssh" => ssh::run(cmd, input, ctx),
+        "palettes/combine" => combine::run(cmd, input, ctx),
+        // Actions
+        "actions/cmd" | "actions/copy" | "actions/open" | "actions/notify" | "actions/term" => {
+            actions::run(path.trim_start_matches("actions/"), cmd, input, ctx)
+        }
         // Frontends
-        "frontends/fzf" => fzf::run(cmd, input),
-        "frontends/rofi" => rofi::run(cmd, input),
+        "frontends/fzf" => fzf::run(cmd, input, ctx),
+        "frontends/rofi" => rofi::run(cmd, input, ctx),
         "frontends/stdin" => stdin::run(cmd, input),
         _ => {
             eprintln!("unknown builtin: {path}");
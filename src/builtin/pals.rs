@@ -2,12 +2,15 @@ use std::process::Command;
 use serde_json::json;
 
 use crate::config::Config;
+use crate::context::{Context, Session};
 use crate::Cli;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use super::desktop;
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
-        "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "list" => list(&ctx.session),
+        "pick" => pick(input.unwrap_or(""), ctx),
         _ => {
             eprintln!("pals: unknown command: {cmd}");
             std::process::exit(1);
@@ -15,12 +18,10 @@ pub fn run(cmd: &str, input: Option<&str>) -> String {
     }
 }
 
-fn list() -> String {
-    let config_file = std::env::var("_PAL_CONFIG").unwrap_or_else(|_| "pal.default.toml".into());
-    let cli = Cli { config: config_file.clone(), ..Default::default() };
-    let cfg = Config::load(&config_file, &cli).unwrap_or_else(|_| {
-        return Config { general: Default::default(), palette: Default::default(), frontend: Default::default() };
-    });
+fn list(session: &Session) -> String {
+    let config_file = session.config_file();
+    let cli = Cli { config: config_file.clone(), profile: session.profile.clone(), ..Default::default() };
+    let cfg = Config::load(&config_file, &cli).unwrap_or_default();
 
     let mut palettes: Vec<_> = cfg.palette.iter().collect();
     palettes.sort_by_key(|(name, _)| (*name).clone());
@@ -33,24 +34,22 @@ fn list() -> String {
         .join("\n")
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, ctx: &Context) -> String {
     let item: serde_json::Value = serde_json::from_str(input).unwrap_or_default();
     let palette = item.get("id").and_then(|v| v.as_str()).unwrap_or("");
 
-    if palette.is_empty() {
+    if palette.is_empty() || desktop::dry_run("pals", &format!("run palette {palette}"), ctx) {
         return String::new();
     }
 
-    let config_file = std::env::var("_PAL_CONFIG").unwrap_or_else(|_| "pal.default.toml".into());
-    let frontend = std::env::var("_PAL_FRONTEND").unwrap_or_default();
-
+    let config_file = ctx.session.config_file();
     let mut args = vec!["-c", &config_file, "run"];
-    if !frontend.is_empty() {
-        args.push(&frontend);
+    if let Some(ref frontend) = ctx.session.frontend {
+        args.push(frontend);
     }
     args.push(palette);
 
-    let _ = Command::new("pal").args(&args).status();
+    let _ = ctx.apply(&mut Command::new("pal")).args(&args).status();
 
     String::new()
 }
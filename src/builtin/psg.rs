@@ -3,10 +3,14 @@ use std::process::Command;
 
 use serde_json::json;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use crate::context::Context;
+
+use super::desktop;
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
         "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "pick" => pick(input.unwrap_or(""), ctx),
         _ => {
             eprintln!("psg: unknown command: {cmd}");
             std::process::exit(1);
@@ -97,11 +101,11 @@ fn list() -> String {
         .join("\n")
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, ctx: &Context) -> String {
     let item: serde_json::Value = serde_json::from_str(input).unwrap_or_default();
     let pid = item.get("pid").and_then(|v| v.as_u64()).unwrap_or(0);
 
-    if pid == 0 {
+    if pid == 0 || desktop::dry_run("psg", &format!("kill {pid}"), ctx) {
         return String::new();
     }
 
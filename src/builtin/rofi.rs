@@ -1,15 +1,17 @@
 use std::io::Write;
 use std::process::{Command, Stdio};
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use crate::context::Context;
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
         "run" => {
             let items = input.unwrap_or("");
             let (display, raw_items) = format_items(items);
-            pick_display(&display, &raw_items)
+            pick_display(&display, &raw_items, ctx)
         }
-        "prompt" => prompt(input.unwrap_or("Input")),
-        "input_run" => input_run(input.unwrap_or("Input")),
+        "prompt" => prompt(input.unwrap_or("Input"), ctx),
+        "input_run" => input_run(input.unwrap_or("Input"), ctx),
         _ => {
             eprintln!("rofi: unknown command: {cmd}");
             std::process::exit(1);
@@ -17,8 +19,8 @@ pub fn run(cmd: &str, input: Option<&str>) -> String {
     }
 }
 
-fn prompt(message: &str) -> String {
-    let child = Command::new("rofi")
+fn prompt(message: &str, ctx: &Context) -> String {
+    let child = ctx.command("rofi")
         .args(["-dmenu", "-p", message, "-l", "0"])
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -42,9 +44,9 @@ fn prompt(message: &str) -> String {
 /// Launch rofi in script mode for input palettes.
 /// Rofi calls `pal _rofi-input <palette>` which handles the interaction.
 /// Pick is handled internally by the script handler, so this returns empty.
-fn input_run(message: &str) -> String {
-    let palette = std::env::var("_PAL_PALETTE").unwrap_or_default();
-    let config = std::env::var("_PAL_CONFIG").unwrap_or_default();
+fn input_run(message: &str, ctx: &Context) -> String {
+    let palette = ctx.session.palette.as_deref().unwrap_or_default();
+    let config = ctx.session.config_file();
     let exe = std::env::current_exe()
         .unwrap_or_else(|_| "pal".into())
         .to_string_lossy()
@@ -56,7 +58,7 @@ fn input_run(message: &str) -> String {
     );
     let modi = format!("{}:{}", message, script);
 
-    let child = Command::new("rofi")
+    let child = ctx.command("rofi")
         .args(["-show", message, "-modi", &modi, "-no-sort"])
         .spawn()
         .unwrap_or_else(|e| {
@@ -139,9 +141,9 @@ pub fn has_blocks() -> bool {
 
 /// Launch rofi in blocks mode for live input palettes.
 /// The event loop runs in `pal _rofi-blocks-input` which rofi spawns as a subprocess.
-pub fn blocks_input_run(message: &str) {
-    let palette = std::env::var("_PAL_PALETTE").unwrap_or_default();
-    let config = std::env::var("_PAL_CONFIG").unwrap_or_default();
+pub fn blocks_input_run(message: &str, ctx: &Context) {
+    let palette = ctx.session.palette.as_deref().unwrap_or_default();
+    let config = ctx.session.config_file();
     let exe = std::env::current_exe()
         .unwrap_or_else(|_| "pal".into())
         .to_string_lossy()
@@ -153,7 +155,7 @@ pub fn blocks_input_run(message: &str) {
     );
 
     let prompt = format!("{message}> ");
-    let child = Command::new("rofi")
+    let child = ctx.command("rofi")
         .args(["-modi", "blocks", "-show", "blocks", "-blocks-wrap", &wrap, "-no-sort", "-p", &prompt])
         .spawn()
         .unwrap_or_else(|e| {
@@ -255,8 +257,8 @@ pub fn format_items(items: &str) -> (String, Vec<String>) {
 }
 
 /// Run rofi picker with pre-formatted display, return selected raw JSON item.
-pub fn pick_display(display: &str, raw_items: &[String]) -> String {
-    let mut child = Command::new("rofi")
+pub fn pick_display(display: &str, raw_items: &[String], ctx: &Context) -> String {
+    let mut child = ctx.command("rofi")
         .args(["-dmenu", "-i", "-p", "pal", "-show-icons", "-markup-rows", "-format", "i"])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -3,10 +3,15 @@ use std::fs;
 use std::path::PathBuf;
 use serde_json::json;
 
-pub fn run(cmd: &str, input: Option<&str>) -> String {
+use crate::action::shell_quote;
+use crate::context::Context;
+
+use super::desktop;
+
+pub fn run(cmd: &str, input: Option<&str>, ctx: &Context) -> String {
     match cmd {
         "list" => list(),
-        "pick" => pick(input.unwrap_or("")),
+        "pick" => pick(input.unwrap_or(""), ctx),
         _ => {
             eprintln!("ssh: unknown command: {cmd}");
             String::new()
@@ -73,8 +78,8 @@ fn list() -> String {
                 let line = line.trim();
                 if line.to_lowercase().starts_with("include ") {
                     let pattern = line[8..].trim();
-                    let expanded = if pattern.starts_with("~/") {
-                        home.join(&pattern[2..])
+                    let expanded = if let Some(rest) = pattern.strip_prefix("~/") {
+                        home.join(rest)
                     } else if pattern.starts_with('/') {
                         PathBuf::from(pattern)
                     } else {
@@ -122,7 +127,7 @@ fn parse_config_hosts(content: &str, hosts: &mut HashSet<String>) {
     }
 }
 
-fn pick(input: &str) -> String {
+fn pick(input: &str, ctx: &Context) -> String {
     let item: serde_json::Value = match serde_json::from_str(input) {
         Ok(v) => v,
         Err(_) => return String::new(),
@@ -133,55 +138,19 @@ fn pick(input: &str) -> String {
         return String::new();
     }
 
-    let frontend = std::env::var("_PAL_FRONTEND").unwrap_or_default();
-    let is_stdio = matches!(frontend.as_str(), "fzf" | "stdin" | "");
-
-    if is_stdio {
-        // Run ssh directly - we're in a terminal
-        let _ = std::process::Command::new("ssh")
-            .arg(host)
-            .status();
-    } else {
-        // Non-stdio frontend (rofi, etc.) - copy to clipboard and notify
-        let cmd = format!("ssh {}", host);
-        copy_and_notify(&cmd);
+    // Runs through `bash -c`; the host comes from files pal doesn't control
+    let cmd = format!("ssh {}", shell_quote(host));
+    if desktop::dry_run("ssh", &format!("run {cmd}"), ctx) {
+        return String::new();
+    }
+    if desktop::has_tty(ctx) || desktop::terminal(ctx).is_some() {
+        if let Err(e) = desktop::run_in_terminal(&cmd, ctx) {
+            eprintln!("ssh: {e}");
+        }
+    } else if desktop::copy(&cmd, false) {
+        // No terminal to run in - copy to clipboard and notify
+        desktop::notify("Copied", &cmd);
     }
 
     String::new()
 }
-
-fn copy_and_notify(text: &str) {
-    use std::process::{Command, Stdio};
-    use std::io::Write;
-
-    // Copy to clipboard
-    let copied = if Command::new("which").arg("wl-copy").output()
-        .map(|o| o.status.success()).unwrap_or(false) {
-        Command::new("wl-copy")
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut c| {
-                c.stdin.as_mut().unwrap().write_all(text.as_bytes())?;
-                c.wait()
-            }).is_ok()
-    } else if Command::new("which").arg("xclip").output()
-        .map(|o| o.status.success()).unwrap_or(false) {
-        Command::new("xclip")
-            .args(["-selection", "clipboard"])
-            .stdin(Stdio::piped())
-            .spawn()
-            .and_then(|mut c| {
-                c.stdin.as_mut().unwrap().write_all(text.as_bytes())?;
-                c.wait()
-            }).is_ok()
-    } else {
-        false
-    };
-
-    // Show notification
-    if copied {
-        let _ = Command::new("notify-send")
-            .args(["-t", "2000", "Copied", text])
-            .spawn();
-    }
-}
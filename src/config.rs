@@ -1,11 +1,15 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use figment::{Figment, providers::{Format, Toml, Env}};
-use serde::{Deserialize, Serialize};
+use figment::{Figment, Metadata, Profile as FigmentProfile, Provider, Source};
+use figment::providers::{Format, Toml, Env};
+use figment::value::{Dict, Map, Value};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::Cli;
+use crate::{util, Cli};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
     #[serde(default)]
     pub general: General,
@@ -13,23 +17,62 @@ pub struct Config {
     pub palette: HashMap<String, Palette>,
     #[serde(default)]
     pub frontend: HashMap<String, Frontend>,
+    #[serde(default)]
+    pub action: HashMap<String, Action>,
+    #[serde(default)]
+    pub profile: HashMap<String, Profile>,
+    /// Values config strings can refer to as `${var:name}`
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Profile merged into this config, if any
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    /// Where each value came from
+    #[serde(skip)]
+    pub provenance: Provenance,
+}
+
+/// The merged figment and the config files that went into it, to tell which layer set a key
+#[derive(Debug, Default)]
+pub struct Provenance {
+    figment: Figment,
+    /// Config files in load order with the layer they belong to
+    files: Vec<(PathBuf, &'static str)>,
+    profile: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct General {
     #[serde(default = "defaults::palette")]
     pub default_palette: String,
     #[serde(default = "defaults::frontend")]
     pub default_frontend: String,
-    pub env_file: Option<String>,
+    /// Dotenv files loaded into pal's environment before running plugins, in order
+    #[serde(default)]
+    pub env_file: EnvFiles,
+    /// Let env files override variables that are already set
+    #[serde(default = "defaults::enabled")]
+    pub env_override: bool,
+    /// Extra config files merged right after the file listing them, e.g. `conf.d/*.toml`
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Terminal command used to run items that need a terminal, e.g. `kitty -e`
+    pub terminal: Option<String>,
+    /// Never clone or fetch remote plugins
+    #[serde(default)]
+    pub offline: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Palette {
+    /// Palette to inherit all fields from that aren't set here
+    pub extends: Option<String>,
+    /// Plugin: builtin/palettes/<name>, a local path or a remote source like github:user/repo/path
     pub base: Option<String>,
     pub icon: Option<String>,
     pub icon_xdg: Option<String>,
     pub icon_utf: Option<String>,
+    /// Cache formatted items (rofi)
     #[serde(default)]
     pub cache: bool,
     #[serde(default)]
@@ -41,73 +84,285 @@ pub struct Palette {
     pub auto_list: bool,
     #[serde(default)]
     pub auto_pick: bool,
+    /// Data file (JSON lines, JSON array or TOML) listed by auto_list palettes
     pub data: Option<String>,
+    /// Palettes merged by the combine palette
     #[serde(default)]
     pub include: Vec<String>,
-    pub default_action: Option<String>,
+    /// Action(s) run on pick by auto_pick palettes
+    pub default_action: Option<ActionChain>,
+    /// Item field passed to `default_action`
     pub action_key: Option<String>,
+    /// Items need a terminal: run inline on TTY frontends, in a terminal emulator otherwise
+    #[serde(default)]
+    pub terminal: bool,
+    /// Environment for the palette's plugin and the actions its picks run
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Dotenv files read into the palette's environment, in order and below `env`
+    #[serde(default)]
+    pub env_file: EnvFiles,
+    /// Working directory for the palette's plugin and actions
+    pub cwd: Option<String>,
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Frontend {
     pub base: Option<String>,
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+/// Overlay from a `[profile.<name>]` table, merged over the top-level config when selected
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct Profile {
+    /// Hostnames that select this profile automatically
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// `general`, `palette`, `frontend` and `action` tables overriding the top-level ones
+    #[serde(flatten)]
+    pub overlay: HashMap<String, serde_json::Value>,
+}
+
+/// User-defined action from an `[action.<name>]` table
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Action {
+    /// Plugin path or name of another action to build on
+    pub base: Option<String>,
+    /// Shell template run instead of a plugin, e.g. `kitty -e ssh {{value}}`
+    pub template: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Action reference: a plain name or `{ name = "copy", args = { ... } }`
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ActionRef {
+    Name(String),
+    Spec {
+        name: String,
+        #[serde(default)]
+        args: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+impl ActionRef {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Name(name) | Self::Spec { name, .. } => name,
+        }
+    }
+
+    pub fn args(&self) -> serde_json::Map<String, serde_json::Value> {
+        match self {
+            Self::Name(_) => Default::default(),
+            Self::Spec { args, .. } => args.clone(),
+        }
+    }
+
+    /// Copy of this reference with an extra arg set
+    pub fn with_arg(&self, key: &str, value: serde_json::Value) -> Self {
+        let mut args = self.args();
+        args.insert(key.to_string(), value);
+        Self::Spec { name: self.name().to_string(), args }
+    }
+}
+
+/// One action or a list of actions run as a pipeline
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct ActionChain(pub Vec<ActionRef>);
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(item) => vec![item],
+            OneOrMany::Many(items) => items,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ActionChain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(OneOrMany::deserialize(deserializer)?.into()))
+    }
+}
+
+impl JsonSchema for ActionChain {
+    fn schema_name() -> String {
+        "ActionChain".into()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        OneOrMany::<ActionRef>::json_schema(gen)
+    }
+}
+
+/// One dotenv file or a list of them
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct EnvFiles(pub Vec<String>);
+
+impl<'de> Deserialize<'de> for EnvFiles {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(OneOrMany::deserialize(deserializer)?.into()))
+    }
+}
+
+impl JsonSchema for EnvFiles {
+    fn schema_name() -> String {
+        "EnvFiles".into()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        OneOrMany::<String>::json_schema(gen)
+    }
+}
+
+impl Provenance {
+    /// Layer that set a dotted key, e.g. `user config (/home/me/.config/pal/config.toml)`
+    /// or `env PAL_CONFIG__GENERAL__DEFAULT_FRONTEND`. None for keys no layer set.
+    pub fn source(&self, key: &str) -> Option<String> {
+        let value = self.figment.find_value(key).ok()?;
+        let metadata = self.figment.get_metadata(value.tag())?;
+        let layer = match &metadata.source {
+            Some(Source::File(path)) => {
+                let layer = self.files.iter().find(|(f, _)| f == path).map_or("file", |(_, layer)| layer);
+                format!("{layer} ({})", path.display())
+            }
+            Some(Source::Custom(name)) => name.clone(),
+            Some(_) => "command line".into(),
+            // Only the env provider has no source
+            None => format!("env {ENV_PREFIX}{}", key.to_uppercase().replace('.', "__")),
+        };
+
+        // Values from the active profile's overlay carry the same tag as the profile table's
+        let from_profile = self.profile.as_ref().filter(|name| {
+            self.figment
+                .find_value(&format!("profile.{name}.{key}"))
+                .is_ok_and(|v| v.tag() == value.tag())
+        });
+        Some(match from_profile {
+            Some(name) => format!("profile {name}, {layer}"),
+            None => layer,
+        })
+    }
+}
+
 impl Default for General {
     fn default() -> Self {
         Self {
             default_palette: defaults::palette(),
             default_frontend: defaults::frontend(),
-            env_file: None,
+            env_file: EnvFiles::default(),
+            env_override: true,
+            include: vec![],
+            terminal: None,
+            offline: false,
         }
     }
 }
 
+/// Env vars overriding config keys, nested with `__`: `PAL_CONFIG__GENERAL__DEFAULT_FRONTEND=rofi`
+const ENV_PREFIX: &str = "PAL_CONFIG__";
+/// Env var selecting the profile
+const PROFILE_VAR: &str = "PAL_PROFILE";
+
+/// Whether pal reads `name` when loading its config. Item fields aren't exported under these
+/// names, so a picked item can't change the config of the pal calls its action makes.
+pub fn is_reserved_var(name: &str) -> bool {
+    name == PROFILE_VAR || name.starts_with(ENV_PREFIX)
+}
+
 mod defaults {
     pub fn palette() -> String { "combine".into() }
     pub fn frontend() -> String { "fzf".into() }
+    pub fn enabled() -> bool { true }
 }
 
 impl Config {
-    pub fn load(path: &str, cli: &Cli) -> Result<Self, figment::Error> {
-        let user_config = dirs::config_dir()
-            .map(|p| p.join("pal/config.toml"))
-            .unwrap_or_default();
+    /// Load the merged config. The error is boxed, figment's is large for a Result.
+    pub fn load(path: &str, cli: &Cli) -> Result<Self, Box<figment::Error>> {
+        Self::load_files(path, cli, true)
+    }
+
+    /// The user config alone: defaults, config.toml and conf.d, without project pal.toml files
+    /// or `--config`, so it's the same wherever pal runs
+    pub fn load_user(cli: &Cli) -> Result<Self, Box<figment::Error>> {
+        Self::load_files("pal.default.toml", cli, false)
+    }
 
-        let mut figment = Figment::new()
-            .merge(Toml::file("pal.default.toml"))
-            .merge(Toml::file(&user_config))
-            .merge(Toml::file("pal.toml"));
+    fn load_files(path: &str, cli: &Cli, project: bool) -> Result<Self, Box<figment::Error>> {
+        let user_config = user_config_path();
+
+        let mut files = vec![];
+        let mut figment = Figment::new();
+        figment = merge_file(figment, Path::new("pal.default.toml"), "default file", &mut files);
+        figment = merge_file(figment, &user_config, "user config", &mut files);
+        // Drop-in files next to the user config
+        if let Some(dir) = user_config.parent() {
+            for file in util::glob(&dir.join("conf.d/*.toml")) {
+                figment = figment.merge(Toml::file(&file));
+                files.push((file, "conf.d"));
+            }
+        }
+        // Project configs from the repo (or filesystem) root down to cwd, nearest last
+        if project {
+            for file in project_configs("pal.toml") {
+                figment = merge_file(figment, &file, "pal.toml", &mut files);
+            }
+        }
 
         // Only merge explicit --config if it's not the default
         let is_default = path.ends_with("pal.default.toml") || path == "pal.default.toml";
         if !is_default {
-            figment = figment.merge(Toml::file(path));
+            figment = merge_file(figment, Path::new(path), "--config", &mut files);
         }
 
-        let mut figment = figment.merge(Env::prefixed("PAL_").split("_"));
+        // Profile overlays sit above the config files, below env and CLI overrides
+        let profile = select_profile(&figment, cli.profile.as_deref())?;
+        if let Some(ref name) = profile {
+            let overlay = figment.focus(&format!("profile.{name}"));
+            figment = figment.merge(overlay);
+        }
+
+        // A prefix of its own, so other `PAL_` variables never land in the config
+        let mut figment = figment.merge(Env::prefixed(ENV_PREFIX).split("__"));
 
         if let Some(ref level) = cli.log_level {
             figment = figment.merge(("general.log_level", level.as_str()));
         }
 
+        let figment = resolve_data_paths(figment);
+        let figment = resolve_extends(figment)?;
         let mut config: Self = figment.extract()?;
+        config.provenance = Provenance { figment, files, profile: profile.clone() };
+        config.active_profile = profile;
+        crate::interpolate::set_vars(config.vars.clone());
         config.resolve_plugin_defaults();
         config.expand_data_paths(&user_config);
         Ok(config)
     }
 
-    /// Expand relative data paths to absolute paths (relative to user config dir)
-    fn expand_data_paths(&mut self, user_config: &std::path::Path) {
-        let config_dir = user_config.parent().unwrap_or(std::path::Path::new(""));
+    /// Expand relative data paths that didn't come from a config file (relative to user config dir)
+    fn expand_data_paths(&mut self, user_config: &Path) {
+        let config_dir = user_config.parent().unwrap_or(Path::new(""));
         for palette in self.palette.values_mut() {
             if let Some(data) = palette.data.take() {
-                // Skip if already absolute, home-relative, or remote
-                if data.starts_with('/') || data.starts_with("~/") || data.starts_with("github:") {
+                // Skip if already absolute, home-relative, remote, or a `${...}` reference
+                if data.starts_with('/') || data.starts_with("~/") || crate::remote::is_remote(&data) || data.starts_with("${") {
                     palette.data = Some(data);
                     continue;
                 }
@@ -118,71 +373,317 @@ impl Config {
         }
     }
 
-    /// Fill in missing palette fields from plugin.toml files
+    /// Fill in missing palette fields from plugin.toml files.
+    /// Remote plugins are only read if already cloned; `resolve_palette` fetches the rest on use.
     fn resolve_plugin_defaults(&mut self) {
-        for (_name, palette) in self.palette.iter_mut() {
-            if let Some(base) = &palette.base {
-                if let Some(plugin) = load_plugin_toml(base) {
-                    if palette.icon.is_none() {
-                        palette.icon = plugin.get("icon").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if palette.icon_xdg.is_none() {
-                        palette.icon_xdg = plugin.get("icon_xdg").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if palette.icon_utf.is_none() {
-                        palette.icon_utf = plugin.get("icon_utf").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if !palette.auto_list {
-                        palette.auto_list = plugin.get("auto_list").and_then(|v| v.as_bool()).unwrap_or(false);
-                    }
-                    if !palette.auto_pick {
-                        palette.auto_pick = plugin.get("auto_pick").and_then(|v| v.as_bool()).unwrap_or(false);
-                    }
-                    if palette.default_action.is_none() {
-                        palette.default_action = plugin.get("default_action").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if palette.action_key.is_none() {
-                        palette.action_key = plugin.get("action_key").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if !palette.input {
-                        palette.input = plugin.get("input").and_then(|v| v.as_bool()).unwrap_or(false);
-                    }
-                    if palette.input_prompt.is_none() {
-                        palette.input_prompt = plugin.get("input_prompt").and_then(|v| v.as_str()).map(String::from);
-                    }
-                    if !palette.live {
-                        palette.live = plugin.get("live").and_then(|v| v.as_bool()).unwrap_or(false);
-                    }
-                }
+        for palette in self.palette.values_mut() {
+            if let Some(plugin) = palette.base.as_deref().and_then(|b| load_plugin_toml(b, false)) {
+                palette.apply_plugin_defaults(&plugin);
             }
         }
     }
+
+    /// Make a palette that's about to be used ready, cloning its remote plugin if needed
+    pub fn resolve_palette(&mut self, name: &str) -> Result<(), String> {
+        let Some(palette) = self.palette.get_mut(name) else { return Ok(()) };
+        let Some(base) = palette.base.clone() else { return Ok(()) };
+        if !crate::remote::is_remote(&base) || crate::remote::local_path(&base).is_some() {
+            return Ok(());
+        }
+        let plugin = load_plugin_toml(&base, true)
+            .ok_or_else(|| format!("palette '{name}': plugin not available: {base}"))?;
+        palette.apply_plugin_defaults(&plugin);
+        Ok(())
+    }
+}
+
+impl Palette {
+    fn apply_plugin_defaults(&mut self, plugin: &toml::Value) {
+        if self.icon.is_none() {
+            self.icon = plugin.get("icon").and_then(|v| v.as_str()).map(String::from);
+        }
+        if self.icon_xdg.is_none() {
+            self.icon_xdg = plugin.get("icon_xdg").and_then(|v| v.as_str()).map(String::from);
+        }
+        if self.icon_utf.is_none() {
+            self.icon_utf = plugin.get("icon_utf").and_then(|v| v.as_str()).map(String::from);
+        }
+        if !self.auto_list {
+            self.auto_list = plugin.get("auto_list").and_then(|v| v.as_bool()).unwrap_or(false);
+        }
+        if !self.auto_pick {
+            self.auto_pick = plugin.get("auto_pick").and_then(|v| v.as_bool()).unwrap_or(false);
+        }
+        if self.default_action.is_none() {
+            self.default_action = plugin.get("default_action").cloned().and_then(|v| v.try_into().ok());
+        }
+        if self.action_key.is_none() {
+            self.action_key = plugin.get("action_key").and_then(|v| v.as_str()).map(String::from);
+        }
+        if !self.input {
+            self.input = plugin.get("input").and_then(|v| v.as_bool()).unwrap_or(false);
+        }
+        if self.input_prompt.is_none() {
+            self.input_prompt = plugin.get("input_prompt").and_then(|v| v.as_str()).map(String::from);
+        }
+        if !self.terminal {
+            self.terminal = plugin.get("terminal").and_then(|v| v.as_bool()).unwrap_or(false);
+        }
+        if !self.live {
+            self.live = plugin.get("live").and_then(|v| v.as_bool()).unwrap_or(false);
+        }
+    }
+}
+
+/// Merge a config file, then the files matched by its `general.include` globs
+/// (relative to the file). Included files don't include further.
+fn merge_file(figment: Figment, file: &Path, layer: &'static str, files: &mut Vec<(PathBuf, &'static str)>) -> Figment {
+    let Some(file) = find_config_file(file) else { return figment };
+    let mut figment = figment.merge(Toml::file(&file));
+    files.push((file.clone(), layer));
+
+    // Parse errors surface when the figment is extracted
+    let includes = std::fs::read_to_string(&file)
+        .ok()
+        .and_then(|content| content.parse::<toml::Value>().ok())
+        .and_then(|value| value.get("general")?.get("include")?.as_array().cloned())
+        .unwrap_or_default();
+    let dir = file.parent().unwrap_or(Path::new(""));
+    for pattern in includes.iter().filter_map(|p| p.as_str()) {
+        let pattern = match pattern.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => dir.join(pattern),
+        };
+        for included in util::glob(&pattern) {
+            figment = figment.merge(Toml::file(&included));
+            files.push((included, "include"));
+        }
+    }
+    figment
+}
+
+/// Locate a config file like figment does: relative paths are looked up in the
+/// working directory and its parents
+fn find_config_file(file: &Path) -> Option<PathBuf> {
+    if file.is_absolute() {
+        return file.exists().then(|| file.to_path_buf());
+    }
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors().map(|dir| dir.join(file)).find(|p| p.is_file())
+}
+
+/// Every `name` file from cwd up to the enclosing git root (or filesystem root), farthest first
+fn project_configs(name: &str) -> Vec<PathBuf> {
+    let Ok(cwd) = std::env::current_dir() else { return vec![] };
+    let mut files = vec![];
+    for dir in cwd.ancestors() {
+        let file = dir.join(name);
+        if file.is_file() {
+            files.push(file);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    files.reverse();
+    files
+}
+
+/// Make relative palette `data`, `env_file` and `cwd` paths, plugin `base` paths and
+/// `general.env_file` absolute against the directory of the file that set them
+fn resolve_data_paths(mut figment: Figment) -> Figment {
+    let names = |table: &str| -> Vec<String> {
+        figment.extract_inner::<HashMap<String, serde_json::Value>>(table).unwrap_or_default().into_keys().collect()
+    };
+    let keys: Vec<_> = names("palette")
+        .iter()
+        .flat_map(|name| ["data", "env_file", "cwd", "base"].map(|field| format!("palette.{name}.{field}")))
+        .chain(names("frontend").iter().map(|name| format!("frontend.{name}.base")))
+        .chain(names("action").iter().map(|name| format!("action.{name}.base")))
+        .chain(["general.env_file".to_string()])
+        .collect();
+    for key in keys {
+        let Ok(value) = figment.find_value(&key) else { continue };
+        // `builtin/...` isn't a directory, an action base without a `/` names another action
+        let named = |base: &str| base.starts_with("builtin/") || (key.starts_with("action.") && !base.contains('/'));
+        if key.ends_with(".base") && value.as_str().is_some_and(named) {
+            continue;
+        }
+        let file = figment
+            .get_metadata(value.tag())
+            .and_then(|m| m.source.as_ref())
+            .and_then(|s| s.file_path());
+        let Some(dir) = file.and_then(|f| f.parent()).map(Path::to_path_buf) else { continue };
+        let value = match value {
+            Value::Array(tag, paths) => Value::Array(tag, paths.into_iter().map(|p| absolute(p, &dir)).collect()),
+            value => absolute(value, &dir),
+        };
+        figment = figment.merge(Tagged::new(&key, value));
+    }
+    figment
+}
+
+/// A relative path joined onto `dir`. Absolute, home-relative and remote paths and `${...}`
+/// references are returned as is.
+fn absolute(value: Value, dir: &Path) -> Value {
+    match value.as_str() {
+        Some(path) if !(path.starts_with('/') || path.starts_with("~/") || crate::remote::is_remote(path) || path.starts_with("${")) => {
+            Value::String(value.tag(), dir.join(path).to_string_lossy().into_owned())
+        }
+        _ => value,
+    }
+}
+
+/// Fill palettes that `extends` another palette with the fields they don't set themselves.
+/// Joined into the figment so inherited values never override the palette's own.
+fn resolve_extends(figment: Figment) -> Result<Figment, Box<figment::Error>> {
+    let Some(palettes) = figment.find_value("palette").ok().and_then(|v| v.into_dict()) else {
+        return Ok(figment);
+    };
+    let mut inherited = Dict::new();
+    for name in palettes.keys() {
+        if palettes[name].find_ref("extends").is_none() {
+            continue;
+        }
+        let palette = inherit(name, &palettes, &mut vec![]).map_err(figment::Error::from)?;
+        inherited.insert(name.clone(), palette);
+    }
+    Ok(figment.join(Tagged::new("palette", inherited.into())))
+}
+
+/// A palette's fields joined with those of its `extends` chain
+fn inherit(name: &str, palettes: &Dict, chain: &mut Vec<String>) -> Result<Value, String> {
+    if chain.iter().any(|n| n == name) {
+        chain.push(name.to_string());
+        return Err(format!("palette extends cycle: {}", chain.join(" -> ")));
+    }
+    let mut palette = palettes[name].clone();
+    let Some(parent) = palette.find_ref("extends").and_then(|v| v.as_str()).map(String::from) else {
+        return Ok(palette);
+    };
+    if !palettes.contains_key(&parent) {
+        return Err(format!("palette '{name}' extends unknown palette '{parent}'"));
+    }
+
+    chain.push(name.to_string());
+    let inherited = inherit(&parent, palettes, chain)?;
+    chain.pop();
+    join_values(&mut palette, inherited);
+    Ok(palette)
+}
+
+/// Add keys from `other` that `value` doesn't have, recursing into tables
+fn join_values(value: &mut Value, other: Value) {
+    let (Value::Dict(_, value), Value::Dict(_, other)) = (value, other) else { return };
+    for (key, theirs) in other {
+        match value.get_mut(&key) {
+            Some(ours) => join_values(ours, theirs),
+            None => {
+                value.insert(key, theirs);
+            }
+        }
+    }
+}
+
+/// Values taken from the figment itself. Unlike `Serialized`, they keep their tags,
+/// so provenance still points at the file that set them.
+struct Tagged(Dict);
+
+impl Tagged {
+    fn new(key: &str, value: Value) -> Self {
+        Self(figment::util::nest(key, value).into_dict().unwrap_or_default())
+    }
 }
 
-/// Load plugin.toml or builtin.toml section
-fn load_plugin_toml(base: &str) -> Option<toml::Value> {
+impl Provider for Tagged {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("pal")
+    }
+
+    fn data(&self) -> Result<Map<FigmentProfile, Dict>, figment::Error> {
+        Ok(FigmentProfile::Default.collect(self.0.clone()))
+    }
+}
+
+/// Pick the profile to apply: `--profile`, then `PAL_PROFILE`, then the profile inherited
+/// from a parent pal, then a profile named after the hostname or listing it in `hosts`
+fn select_profile(figment: &Figment, explicit: Option<&str>) -> Result<Option<String>, Box<figment::Error>> {
+    let profiles: HashMap<String, Profile> = figment.extract_inner("profile").unwrap_or_default();
+
+    let requested = explicit
+        .map(String::from)
+        .or_else(|| std::env::var(PROFILE_VAR).ok())
+        .or_else(|| std::env::var("_PAL_PROFILE").ok())
+        .filter(|name| !name.is_empty());
+    if let Some(name) = requested {
+        if !profiles.contains_key(&name) {
+            return Err(Box::new(format!("profile not found: {name}").into()));
+        }
+        return Ok(Some(name));
+    }
+
+    let Some(host) = hostname() else { return Ok(None) };
+    Ok(profiles
+        .iter()
+        .filter(|(name, profile)| **name == host || profile.hosts.contains(&host))
+        .map(|(name, _)| name.clone())
+        .min())
+}
+
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if ret != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let host = String::from_utf8_lossy(&buf[..len]).into_owned();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Path of the user config, `~/.config/pal/config.toml`
+pub fn user_config_path() -> PathBuf {
+    dirs::config_dir()
+        .map(|p| p.join("pal/config.toml"))
+        .unwrap_or_default()
+}
+
+/// Load plugin.toml or builtin.toml section.
+/// Remote plugins are cloned only when `fetch` is set; results are cached per base
+/// so nested config loads (combine, pals) don't read them again.
+pub fn load_plugin_toml(base: &str, fetch: bool) -> Option<toml::Value> {
+    use std::sync::Mutex;
     use crate::util;
 
-    if let Some(rest) = base.strip_prefix("builtin/") {
+    static CACHE: Mutex<Option<HashMap<String, toml::Value>>> = Mutex::new(None);
+    if let Some(cached) = CACHE.lock().unwrap().as_ref().and_then(|c| c.get(base)) {
+        return Some(cached.clone());
+    }
+
+    let plugin = if let Some(rest) = base.strip_prefix("builtin/") {
         // Load from builtin.toml
         let parts: Vec<&str> = rest.split('/').collect();
         let toml: toml::Value = include_str!("builtin/builtin.toml").parse().ok()?;
-        let section = parts.iter().fold(toml, |v, key| {
+        parts.iter().fold(toml, |v, key| {
             v.get(key).cloned().unwrap_or(toml::Value::Table(Default::default()))
-        });
-        Some(section)
-    } else if base.starts_with("github:") {
-        // Load from remote plugin - ensure it's cloned first
-        let local_path = crate::remote::ensure_github(base)?;
-        let plugin_toml = local_path.join("plugin.toml");
-        let content = std::fs::read_to_string(plugin_toml).ok()?;
-        content.parse().ok()
+        })
+    } else if crate::remote::is_remote(base) {
+        // Load from remote plugin - only clone it when asked to
+        let local_path = if fetch {
+            crate::remote::ensure(base)?
+        } else {
+            crate::remote::local_path(base)?
+        };
+        let content = std::fs::read_to_string(local_path.join("plugin.toml")).ok()?;
+        content.parse().ok()?
     } else {
         // Load from plugin.toml
         let expanded = util::expand_path(base);
-        let plugin_toml = expanded.join("plugin.toml");
-        let content = std::fs::read_to_string(plugin_toml).ok()?;
-        content.parse().ok()
-    }
-}
+        let content = std::fs::read_to_string(expanded.join("plugin.toml")).ok()?;
+        content.parse().ok()?
+    };
 
+    CACHE.lock().unwrap().get_or_insert_with(HashMap::new).insert(base.to_string(), plugin.clone());
+    Some(plugin)
+}
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+use std::process;
+
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+use crate::config::{self, Config};
+use crate::{plugins, schema, util};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+/// Print the effective config, or the part under a dotted key. TOML output notes the
+/// layer each value came from; `palette.<name>` includes the plugin.toml fields the plugin sees.
+pub fn show(cfg: &Config, key: Option<&str>, format: Format) {
+    let value = select(cfg, key.unwrap_or("")).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    let source = |path: &str| source(cfg, path);
+
+    match format {
+        Format::Json => match serde_json::to_string_pretty(&value) {
+            Ok(s) => println!("{s}"),
+            Err(e) => {
+                eprintln!("failed to serialize config: {e}");
+                process::exit(1);
+            }
+        },
+        Format::Toml => match value {
+            toml::Value::Table(_) => {
+                let mut doc: DocumentMut = toml::to_string(&value)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_default();
+                annotate(doc.as_table_mut(), key.unwrap_or(""), &source);
+                print!("{doc}");
+            }
+            value => println!("{value}  # {}", source(key.unwrap_or(""))),
+        },
+    }
+}
+
+/// Config value at a dotted key. Under `palette.<name>` that's the palette merged over its
+/// plugin.toml, as the plugin sees it.
+pub fn select(cfg: &Config, key: &str) -> Result<toml::Value, String> {
+    let mut value = toml::Value::try_from(cfg).map_err(|e| format!("failed to serialize config: {e}"))?;
+    let mut parts = key.split('.').filter(|p| !p.is_empty()).peekable();
+
+    if parts.peek() == Some(&"palette") {
+        parts.next();
+        if let Some(name) = parts.next() {
+            let palette = cfg.palette.get(name).ok_or_else(|| format!("palette not found: {name}"))?;
+            let plugin = palette
+                .base
+                .as_deref()
+                .and_then(|b| config::load_plugin_toml(b, false))
+                .unwrap_or_else(|| toml::Value::Table(Default::default()));
+            value = util::merge_configs(&plugin, palette);
+        } else {
+            value = value.get("palette").cloned().unwrap_or_else(|| toml::Value::Table(Default::default()));
+        }
+    }
+
+    for part in parts {
+        value = value.get(part).cloned().ok_or_else(|| format!("no config key '{key}'"))?;
+    }
+    Ok(value)
+}
+
+/// Layer a key came from. Keys no layer set come from the palette's plugin.toml or pal's defaults.
+fn source(cfg: &Config, key: &str) -> String {
+    if let Some(source) = cfg.provenance.source(key) {
+        return source;
+    }
+    let mut parts = key.split('.');
+    if let (Some("palette"), Some(name), Some(field)) = (parts.next(), parts.next(), parts.next()) {
+        if let Some(base) = cfg.palette.get(name).and_then(|p| p.base.as_deref()) {
+            if config::load_plugin_toml(base, false).is_some_and(|p| p.get(field).is_some()) {
+                return format!("plugin.toml ({base})");
+            }
+        }
+    }
+    "default".into()
+}
+
+/// Add a `# <layer>` comment to every value in a table, recursing into subtables
+fn annotate(table: &mut Table, prefix: &str, source: &dyn Fn(&str) -> String) {
+    for (key, item) in table.iter_mut() {
+        let path = if prefix.is_empty() { key.get().to_string() } else { format!("{prefix}.{}", key.get()) };
+        match item {
+            Item::Value(value) => value.decor_mut().set_suffix(format!("  # {}", source(&path))),
+            Item::Table(table) => annotate(table, &path, source),
+            Item::ArrayOfTables(tables) => {
+                for table in tables.iter_mut() {
+                    table.decor_mut().set_suffix(format!("  # {}", source(&path)));
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+/// Print one value of the effective config. Strings print bare so scripts can use them as is.
+pub fn get(cfg: &Config, key: &str) {
+    match select(cfg, key) {
+        Ok(value) => print_value(&value),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Print one value as set in a single config file
+pub fn get_file(file: &Path, key: &str) {
+    let doc = plugins::load_doc(file);
+    let value: toml::Value = toml::from_str(&doc.to_string()).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", file.display());
+        process::exit(1);
+    });
+    let value = key.split('.').try_fold(value, |value, part| value.get(part).cloned());
+    match value {
+        Some(value) => print_value(&value),
+        None => {
+            eprintln!("{key} is not set in {}", file.display());
+            process::exit(1);
+        }
+    }
+}
+
+/// Set a key in the user config (or `file`), keeping the rest of the file as written.
+/// The value is parsed as TOML (`true`, `3`, `["a", "b"]`) and falls back to a plain string.
+pub fn set(file: Option<&Path>, key: &str, value: &str) {
+    let path = target(file);
+    if !schema::known_config_key(key) {
+        eprintln!("unknown config key '{key}'");
+        process::exit(1);
+    }
+    let mut value = value.parse::<toml_edit::Value>().unwrap_or_else(|_| value.into());
+
+    let mut doc = plugins::load_doc(&path);
+    let (parents, last) = split_key(key);
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        let item = table.entry(part).or_insert_with(|| {
+            let mut t = Table::new();
+            t.set_implicit(true);
+            Item::Table(t)
+        });
+        table = item.as_table_like_mut().unwrap_or_else(|| {
+            eprintln!("{}: {part} is not a table", path.display());
+            process::exit(1);
+        });
+    }
+    // Keep a trailing comment on the line being replaced
+    if let Some(old) = table.get(last).and_then(|i| i.as_value()) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    table.insert(last, Item::Value(value));
+
+    save_validated(&path, &doc);
+}
+
+/// Remove a key from the user config (or `file`)
+pub fn unset(file: Option<&Path>, key: &str) {
+    let path = target(file);
+    let mut doc = plugins::load_doc(&path);
+    let (parents, last) = split_key(key);
+    let table = parents
+        .iter()
+        .try_fold(doc.as_table_mut() as &mut dyn TableLike, |table, part| table.get_mut(part)?.as_table_like_mut());
+    if table.and_then(|t| t.remove(last)).is_none() {
+        eprintln!("{key} is not set in {}", path.display());
+        process::exit(1);
+    }
+
+    save_validated(&path, &doc);
+}
+
+fn target(file: Option<&Path>) -> PathBuf {
+    file.map(Path::to_path_buf).unwrap_or_else(config::user_config_path)
+}
+
+fn split_key(key: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().unwrap_or_default();
+    (parts, last)
+}
+
+/// Save the edited file only if it still deserializes as a config
+fn save_validated(path: &Path, doc: &DocumentMut) {
+    if let Err(e) = toml::from_str::<Config>(&doc.to_string()) {
+        eprintln!("not saving {}: {e}", path.display());
+        process::exit(1);
+    }
+    plugins::save_doc(path, doc);
+}
+
+fn print_value(value: &toml::Value) {
+    match value {
+        toml::Value::String(s) => println!("{s}"),
+        toml::Value::Table(_) => print!("{}", toml::to_string(value).unwrap_or_default()),
+        value => println!("{value}"),
+    }
+}
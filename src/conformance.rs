@@ -0,0 +1,422 @@
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::palette;
+use crate::schema::{self, validate_item};
+use crate::scaffold::Kind;
+
+pub struct Options {
+    pub dir: PathBuf,
+    pub kind: Option<Kind>,
+    /// Query sent to `list` of input palettes
+    pub query: String,
+    pub timeout: Duration,
+}
+
+/// Canned items fed to frontends
+const SAMPLE_ITEMS: &[&str] = &[
+    r#"{"id":"one","name":"One","icon":"face-smile"}"#,
+    r#"{"id":"two","name":"Two","icon":"face-cool"}"#,
+    r#"{"id":"three","name":"Three","icon":"face-wink"}"#,
+];
+
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    warnings: usize,
+}
+
+impl Report {
+    fn pass(&mut self, msg: &str) {
+        self.passed += 1;
+        println!("ok    {msg}");
+    }
+
+    fn fail(&mut self, msg: &str) {
+        self.failed += 1;
+        println!("FAIL  {msg}");
+    }
+
+    fn warn(&mut self, msg: &str) {
+        self.warnings += 1;
+        println!("warn  {msg}");
+    }
+}
+
+/// Output of one plugin invocation
+struct Run {
+    stdout: String,
+    stderr: String,
+    success: bool,
+    elapsed: Duration,
+    timed_out: bool,
+}
+
+/// Run a plugin's commands against the plugin contract and report what breaks.
+/// Exits non-zero if any check fails.
+pub fn run(opts: &Options) {
+    let mut report = Report::default();
+    let dir = opts.dir.canonicalize().unwrap_or_else(|e| {
+        eprintln!("{}: {e}", opts.dir.display());
+        process::exit(1);
+    });
+
+    let plugin = std::fs::read_to_string(dir.join("plugin.toml"))
+        .map_err(|e| e.to_string())
+        .and_then(|content| content.parse::<toml::Value>().map_err(|e| e.to_string()));
+    let plugin = match plugin {
+        Ok(plugin) => plugin,
+        Err(e) => {
+            report.fail(&format!("plugin.toml: {e}"));
+            finish(&report);
+        }
+    };
+    report.pass("plugin.toml parses");
+    if let Err(e) = plugin.clone().try_into::<schema::PluginManifest>() {
+        report.fail(&format!("plugin.toml does not match the plugin schema: {e}"));
+    }
+    if plugin.get("contract_version").is_none() {
+        report.warn("plugin.toml has no contract_version");
+    }
+
+    let kind = opts.kind.unwrap_or_else(|| detect_kind(&dir, &plugin));
+    if opts.kind.is_none() && plugin.get("kind").is_none() {
+        report.warn(&format!("plugin.toml has no kind, tested as a {}", kind.as_str()));
+    }
+    let data_only = plugin.get("command").is_none()
+        && plugin.get("auto_list").and_then(|v| v.as_bool()).unwrap_or(false);
+    if kind == Kind::Palette && data_only {
+        check_data(&dir, &mut report);
+        finish(&report);
+    }
+
+    let Some(exec) = entrypoint(&dir, &plugin, &mut report) else { finish(&report) };
+    let config = serde_json::to_string(&plugin).unwrap_or_default();
+    let tty = kind == Kind::Frontend && plugin.get("tty").and_then(|v| v.as_bool()).unwrap_or(false);
+    let tester = Tester { exec, config, timeout: opts.timeout, tty };
+
+    match kind {
+        Kind::Palette => check_palette(&tester, &plugin, &opts.query, &mut report),
+        Kind::Frontend => check_frontend(&tester, &mut report),
+        Kind::Action => check_action(&tester, &mut report),
+    }
+    finish(&report);
+}
+
+fn finish(report: &Report) -> ! {
+    println!("\n{} passed, {} failed, {} warnings", report.passed, report.failed, report.warnings);
+    process::exit(if report.failed > 0 { 1 } else { 0 });
+}
+
+/// The plugin's `kind`, else a guess from its location (`palettes/`, `frontends/`, `actions/`)
+/// or plugin.toml
+fn detect_kind(dir: &Path, plugin: &toml::Value) -> Kind {
+    if let Some(kind) = plugin.get("kind").and_then(|v| v.clone().try_into::<Kind>().ok()) {
+        return kind;
+    }
+    let parent = dir.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str());
+    match parent {
+        Some("frontends") => Kind::Frontend,
+        Some("actions") => Kind::Action,
+        Some("palettes") => Kind::Palette,
+        _ if plugin.get("capabilities").is_some() => Kind::Frontend,
+        _ => Kind::Palette,
+    }
+}
+
+fn entrypoint(dir: &Path, plugin: &toml::Value, report: &mut Report) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(cmd) = plugin.get("command").and_then(|v| v.as_array()).and_then(|a| a.first()).and_then(|v| v.as_str()) else {
+        report.fail("plugin.toml missing 'command'");
+        return None;
+    };
+    let exec = dir.join(cmd);
+    match std::fs::metadata(&exec) {
+        Ok(meta) if meta.permissions().mode() & 0o111 != 0 => {
+            report.pass(&format!("{cmd} is executable"));
+            Some(exec)
+        }
+        Ok(_) => {
+            report.fail(&format!("{cmd} is not executable"));
+            None
+        }
+        Err(e) => {
+            report.fail(&format!("{cmd}: {e}"));
+            None
+        }
+    }
+}
+
+/// Data-only palettes: validate the bundled data file instead of running anything
+fn check_data(dir: &Path, report: &mut Report) {
+    let Some(path) = ["data.json", "data.toml"].iter().map(|f| dir.join(f)).find(|p| p.exists()) else {
+        report.warn("data-only palette without a bundled data.json or data.toml");
+        return;
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => check_items(&name, &palette::parse_data(&content, &name), report),
+        Err(e) => report.fail(&format!("{name}: {e}")),
+    }
+}
+
+struct Tester {
+    exec: PathBuf,
+    /// plugin.toml as JSON, passed like pal does in `_PAL_PLUGIN_CONFIG`
+    config: String,
+    timeout: Duration,
+    /// Run in a pseudo-terminal that types `TTY_ANSWERS`, for frontends with `tty = true`
+    tty: bool,
+}
+
+/// What a tty frontend reads from its terminal: pick the first item, answer prompts
+const TTY_ANSWERS: &[(&str, &str)] = &[("run", "1\n"), ("prompt", "pal test\n")];
+
+impl Tester {
+    /// Run one plugin command, reporting crashes, timeouts and stderr
+    fn call(&self, cmd: &str, input: &str, label: &str, report: &mut Report) -> Option<Run> {
+        let run = match self.spawn(cmd, input) {
+            Ok(run) => run,
+            Err(e) => {
+                report.fail(&format!("{label}: {e}"));
+                return None;
+            }
+        };
+        let ms = run.elapsed.as_millis();
+        // The usual dispatch fallback in plugin entrypoints
+        let unknown = run.stderr.contains(&format!("Unknown command: {cmd}"));
+        if unknown {
+            report.warn(&format!("{label}: `{cmd}` not implemented"));
+            return None;
+        }
+        if run.timed_out {
+            report.fail(&format!("{label}: no response after {ms}ms (waiting for input?)"));
+        } else if !run.success {
+            report.fail(&format!("{label}: exited with an error ({ms}ms)"));
+        }
+        for line in run.stderr.lines().take(20) {
+            println!("      stderr: {line}");
+        }
+        (!run.timed_out && run.success).then_some(run)
+    }
+
+    fn spawn(&self, cmd: &str, input: &str) -> Result<Run, String> {
+        let mut command = Command::new(&self.exec);
+        command
+            .arg(cmd)
+            .current_dir(self.exec.parent().unwrap_or(Path::new(".")))
+            .env("_PAL_PLUGIN_CONFIG", &self.config)
+            .env("_PAL_DRY_RUN", "1")
+            .env("_PAL_TTY", if self.tty { "1" } else { "0" })
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let pty = match self.tty {
+            true => Some(Pty::open()?),
+            false => None,
+        };
+        let terminal = pty.as_ref().map(|pty| pty.slave.as_raw_fd());
+        // SAFETY: setsid and ioctl are async-signal-safe. A new session has no controlling
+        // terminal, so plugins that try to open /dev/tty fail instead of grabbing ours,
+        // unless they get the pseudo-terminal.
+        unsafe {
+            command.pre_exec(move || {
+                libc::setsid();
+                if let Some(fd) = terminal {
+                    if libc::ioctl(fd, libc::TIOCSCTTY as _, 0) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        let start = Instant::now();
+        let mut child = command.spawn().map_err(|e| e.to_string())?;
+        // Answers wait in the terminal until the plugin reads them; whatever it writes is dropped.
+        // The slave stays open here until the plugin exits, closing the last one hangs it up.
+        let slave = match pty {
+            Some(Pty { mut master, slave }) => {
+                let answer = TTY_ANSWERS.iter().find(|(c, _)| *c == cmd).map_or("\n", |(_, a)| a);
+                master.write_all(answer.as_bytes()).map_err(|e| format!("pseudo-terminal: {e}"))?;
+                read_pipe(Some(master));
+                Some(slave)
+            }
+            None => None,
+        };
+
+        let mut stdin = child.stdin.take();
+        let input = input.to_string();
+        std::thread::spawn(move || {
+            if let Some(ref mut stdin) = stdin {
+                stdin.write_all(input.as_bytes()).ok();
+            }
+        });
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let (status, timed_out) = loop {
+            if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                break (Some(status), false);
+            }
+            if start.elapsed() > self.timeout {
+                // The plugin leads its own process group; take its children down too
+                // SAFETY: plain kill(2) on the group we created
+                unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+                child.wait().ok();
+                break (None, true);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        let elapsed = start.elapsed();
+        drop(slave);
+
+        Ok(Run {
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+            success: status.is_some_and(|s| s.success()),
+            elapsed,
+            timed_out,
+        })
+    }
+}
+
+/// A pseudo-terminal pair; the plugin gets `slave` as its controlling terminal
+struct Pty {
+    master: File,
+    slave: File,
+}
+
+impl Pty {
+    fn open() -> Result<Self, String> {
+        let error = |what: &str| format!("pseudo-terminal: {what}: {}", std::io::Error::last_os_error());
+        // SAFETY: plain POSIX pty calls; each fd is owned by a File as soon as it is open,
+        // and ptsname's static buffer is copied before anything else can call it
+        unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd == -1 {
+                return Err(error("posix_openpt"));
+            }
+            let master = File::from_raw_fd(fd);
+            if libc::grantpt(fd) == -1 || libc::unlockpt(fd) == -1 {
+                return Err(error("unlockpt"));
+            }
+            let name = libc::ptsname(fd);
+            if name.is_null() {
+                return Err(error("ptsname"));
+            }
+            let name = CStr::from_ptr(name).to_owned();
+            let fd = libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+            if fd == -1 {
+                return Err(error("open"));
+            }
+            let slave = File::from_raw_fd(fd);
+            libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+            Ok(Pty { master, slave })
+        }
+    }
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut out = String::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_string(&mut out).ok();
+        }
+        out
+    })
+}
+
+fn check_palette(tester: &Tester, plugin: &toml::Value, query: &str, report: &mut Report) {
+    let input = plugin.get("input").and_then(|v| v.as_bool()).unwrap_or(false);
+    let mut queries = vec![("list", "")];
+    if input {
+        queries.push(("list with query", query));
+    }
+
+    let mut sample = None;
+    for (label, query) in queries {
+        let Some(run) = tester.call("list", query, label, report) else { continue };
+        let count = run.stdout.lines().filter(|l| !l.trim().is_empty()).count();
+        report.pass(&format!("{label} ({}ms, {count} items)", run.elapsed.as_millis()));
+        check_items(label, &run.stdout, report);
+        if sample.is_none() {
+            sample = run.stdout.lines().find(|l| serde_json::from_str::<Value>(l).is_ok()).map(String::from);
+        }
+    }
+
+    // Auto-picked palettes never run their own pick
+    if plugin.get("auto_pick").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return;
+    }
+    let Some(item) = sample else {
+        report.warn("pick skipped, list returned no items");
+        return;
+    };
+    if let Some(run) = tester.call("pick", &item, "pick", report) {
+        report.pass(&format!("pick ({}ms, dry run)", run.elapsed.as_millis()));
+    }
+}
+
+fn check_frontend(tester: &Tester, report: &mut Report) {
+    let items = SAMPLE_ITEMS.join("\n");
+    if let Some(run) = tester.call("run", &items, "run", report) {
+        let ms = run.elapsed.as_millis();
+        let lines: Vec<&str> = run.stdout.lines().filter(|l| !l.trim().is_empty()).collect();
+        let known = |line: &str| {
+            let picked: Option<Value> = serde_json::from_str(line).ok();
+            SAMPLE_ITEMS.iter().any(|item| serde_json::from_str::<Value>(item).ok() == picked)
+        };
+        match lines.as_slice() {
+            [] => report.pass(&format!("run ({ms}ms, nothing picked)")),
+            [line] if known(line) => report.pass(&format!("run ({ms}ms, returned a given item)")),
+            [line] => report.fail(&format!("run: output is not one of the given items: {line}")),
+            _ => report.fail(&format!("run: expected at most one item, got {}", lines.len())),
+        }
+    }
+
+    if let Some(run) = tester.call("prompt", "pal test", "prompt", report) {
+        let ms = run.elapsed.as_millis();
+        match run.stdout.trim_end_matches('\n').lines().count() {
+            0 | 1 => report.pass(&format!("prompt ({ms}ms)")),
+            n => report.fail(&format!("prompt: expected a single line, got {n}")),
+        }
+    }
+}
+
+fn check_action(tester: &Tester, report: &mut Report) {
+    if let Some(run) = tester.call("run", "pal test", "run", report) {
+        report.pass(&format!("run ({}ms, dry run)", run.elapsed.as_millis()));
+    }
+}
+
+/// Validate list output line by line
+fn check_items(label: &str, output: &str, report: &mut Report) {
+    let mut problems = 0;
+    for (i, line) in output.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let (errors, warnings) = match serde_json::from_str::<Value>(line) {
+            Ok(item) => validate_item(&item),
+            Err(e) => (vec![format!("invalid JSON: {e}")], vec![]),
+        };
+        for e in &errors {
+            report.fail(&format!("{label}: line {}: {e}", i + 1));
+        }
+        for w in &warnings {
+            report.warn(&format!("{label}: line {}: {w}", i + 1));
+        }
+        problems += errors.len();
+    }
+    if problems == 0 && !output.trim().is_empty() {
+        report.pass(&format!("{label}: items are valid"));
+    }
+}
@@ -0,0 +1,141 @@
+//! What a plugin invocation runs with: its config, extra environment and working directory.
+//! Passed down explicitly instead of being set on pal's own environment, so palettes in a
+//! combine can't overwrite each other's config and plugins can run side by side.
+
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+/// The invocation as a whole: config file, palette and frontend in use, and flags.
+/// Built once by main and exported to every process pal starts as `_PAL_*` variables,
+/// which is how a nested pal call picks it up again (`Session::inherited`).
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    /// Config file (`_PAL_CONFIG`, its directory is `_PAL_CONFIG_DIR`)
+    pub config: Option<PathBuf>,
+    /// Active config profile (`_PAL_PROFILE`)
+    pub profile: Option<String>,
+    /// `_PAL_PALETTE`
+    pub palette: Option<String>,
+    /// `_PAL_FRONTEND`
+    pub frontend: Option<String>,
+    /// Whether picked items can run in the current terminal (`_PAL_TTY`), unknown if None
+    pub tty: Option<bool>,
+    /// `general.terminal` (`_PAL_TERMINAL`)
+    pub terminal: Option<String>,
+    /// Remote plugins must not be fetched (`_PAL_OFFLINE`)
+    pub offline: bool,
+    /// Malformed plugin output is logged (`_PAL_STRICT`)
+    pub strict: bool,
+    /// Side effects are off (`_PAL_DRY_RUN`, set by `pal plugin test`)
+    pub dry_run: bool,
+    /// Variables from `general.env_file`
+    pub env: Vec<(String, String)>,
+}
+
+impl Session {
+    /// The session a parent pal passed down, empty when pal runs on its own
+    pub fn inherited() -> Self {
+        let var = |key| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let flag = |key| var(key).is_some_and(|v| v == "1");
+        Self {
+            config: var("_PAL_CONFIG").map(PathBuf::from),
+            profile: var("_PAL_PROFILE"),
+            palette: var("_PAL_PALETTE"),
+            frontend: var("_PAL_FRONTEND"),
+            tty: var("_PAL_TTY").map(|v| v == "1"),
+            terminal: var("_PAL_TERMINAL"),
+            offline: flag("_PAL_OFFLINE"),
+            strict: flag("_PAL_STRICT"),
+            dry_run: flag("_PAL_DRY_RUN"),
+            env: vec![],
+        }
+    }
+
+    /// Config file for nested pal calls' `--config`
+    pub fn config_file(&self) -> String {
+        match self.config {
+            Some(ref path) => path.to_string_lossy().into_owned(),
+            None => "pal.default.toml".into(),
+        }
+    }
+
+    /// A variable as the processes pal starts see it: `general.env_file`, then pal's environment
+    pub fn var(&self, key: &str) -> Option<String> {
+        match self.env.iter().rev().find(|(k, _)| k == key) {
+            Some((_, value)) => Some(value.clone()),
+            None => std::env::var(key).ok(),
+        }
+    }
+
+    /// Everything passed on to processes: the `general.env_file` variables and the `_PAL_*` ones
+    fn vars(&self) -> Vec<(String, String)> {
+        let mut vars = self.env.clone();
+        if let Some(ref config) = self.config {
+            vars.push(("_PAL_CONFIG".into(), config.to_string_lossy().into_owned()));
+            if let Some(dir) = config.parent() {
+                vars.push(("_PAL_CONFIG_DIR".into(), dir.to_string_lossy().into_owned()));
+            }
+        }
+        let names = [
+            ("_PAL_PROFILE", &self.profile),
+            ("_PAL_PALETTE", &self.palette),
+            ("_PAL_FRONTEND", &self.frontend),
+            ("_PAL_TERMINAL", &self.terminal),
+        ];
+        vars.extend(names.into_iter().filter_map(|(key, value)| Some((key.into(), value.clone()?))));
+        if let Some(tty) = self.tty {
+            vars.push(("_PAL_TTY".into(), if tty { "1" } else { "0" }.into()));
+        }
+        let flags = [("_PAL_OFFLINE", self.offline), ("_PAL_STRICT", self.strict), ("_PAL_DRY_RUN", self.dry_run)];
+        vars.extend(flags.into_iter().filter(|(_, on)| *on).map(|(key, _)| (key.into(), "1".into())));
+        vars
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// Plugin config, exported to external plugins as `_PAL_PLUGIN_CONFIG`
+    pub config: serde_json::Value,
+    /// Extra environment: a palette's `env` and `env_file`, the picked item's fields, an action's `env`
+    pub env: Vec<(String, String)>,
+    /// Working directory, pal's own if unset
+    pub cwd: Option<PathBuf>,
+    /// The invocation this runs in
+    pub session: Arc<Session>,
+}
+
+impl Context {
+    /// A context with nothing but the session
+    pub fn new(session: &Arc<Session>) -> Self {
+        Self { session: session.clone(), ..Self::default() }
+    }
+
+    /// Copy with more variables, overriding earlier ones of the same name
+    pub fn with_env(&self, env: &[(String, String)]) -> Self {
+        let mut ctx = self.clone();
+        ctx.env.extend_from_slice(env);
+        ctx
+    }
+
+    /// A command for a program pal drives itself, like a frontend or a nested pal.
+    /// It gets the session, not the plugin's env, config and working directory.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.envs(self.session.vars());
+        cmd
+    }
+
+    /// Set up a command to run in this context
+    pub fn apply<'a>(&self, cmd: &'a mut Command) -> &'a mut Command {
+        cmd.envs(self.session.vars()).envs(self.env.iter().cloned());
+        if !self.config.is_null() {
+            cmd.env("_PAL_PLUGIN_CONFIG", self.config.to_string());
+        }
+        if let Some(ref cwd) = self.cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd
+    }
+}
@@ -0,0 +1,318 @@
+//! Dotenv files for `env_file`. Supports `export KEY=value`, `#` comments (inline ones need a
+//! space before the `#`), single-quoted literal values, double-quoted values with `\n`, `\t`,
+//! `\"`, `\\` and `\$` escapes, quoted values spanning several lines, and `${OTHER}`/`$OTHER`
+//! expansion in unquoted and double-quoted values. Malformed lines are reported and skipped.
+
+use crate::util;
+
+/// Variables of env files read in order. Later files see the variables of earlier ones and
+/// override them. Unless `override_existing` is set, variables already set, in `existing` or
+/// pal's environment, keep their value and are left out.
+pub fn read(paths: &[String], override_existing: bool, existing: &[(String, String)]) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    for path in paths {
+        let path = util::expand_path(path);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for (line, e) in parse(&content, override_existing, existing, &mut vars) {
+                    eprintln!("pal: {}: line {line}: {e}", path.display());
+                }
+            }
+            Err(e) => eprintln!("pal: {}: {e}", path.display()),
+        }
+    }
+    if !override_existing {
+        vars.retain(|(key, _)| current(key, existing).is_none());
+    }
+    vars
+}
+
+/// Parse one file, adding its variables to `vars`. Returns the malformed lines with their error.
+fn parse(content: &str, override_existing: bool, existing: &[(String, String)], vars: &mut Vec<(String, String)>) -> Vec<(usize, String)> {
+    let mut errors = Vec::new();
+    let mut parser = Parser { rest: content, line: 1 };
+    while !parser.rest.is_empty() {
+        // Blank lines first, so errors point at the entry's own line
+        parser.skip_blank();
+        let line = parser.line;
+        let entry = parser.entry(&|name| lookup(name, vars, override_existing, existing));
+        match entry {
+            Ok(Some((key, value))) => {
+                vars.retain(|(k, _)| *k != key);
+                vars.push((key, value));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                errors.push((line, e));
+                parser.skip_line();
+            }
+        }
+    }
+    errors
+}
+
+/// Value `${name}` expands to: a variable from the env files read so far, then one already set
+fn lookup(name: &str, vars: &[(String, String)], override_existing: bool, existing: &[(String, String)]) -> String {
+    let current = current(name, existing);
+    if !override_existing && current.is_some() {
+        return current.unwrap_or_default();
+    }
+    match vars.iter().rev().find(|(k, _)| k == name) {
+        Some((_, value)) => value.clone(),
+        None => current.unwrap_or_default(),
+    }
+}
+
+/// A variable set before the env files are read: in `existing`, then pal's environment
+fn current(name: &str, existing: &[(String, String)]) -> Option<String> {
+    match existing.iter().rev().find(|(k, _)| k == name) {
+        Some((_, value)) => Some(value.clone()),
+        None => std::env::var(name).ok(),
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// The next `KEY=value` entry, or None for a blank or comment line
+    fn entry(&mut self, lookup: &dyn Fn(&str) -> String) -> Result<Option<(String, String)>, String> {
+        self.skip_blank();
+        if self.rest.is_empty() {
+            return Ok(None);
+        }
+        if self.rest.starts_with('#') {
+            self.skip_line();
+            return Ok(None);
+        }
+
+        if let Some(rest) = self.rest.strip_prefix("export") {
+            if rest.starts_with([' ', '\t']) {
+                self.rest = rest.trim_start_matches([' ', '\t']);
+            }
+        }
+        let key_len = self.rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(self.rest.len());
+        let key = &self.rest[..key_len];
+        if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("invalid variable name in '{}'", self.current_line()));
+        }
+        self.rest = self.rest[key_len..].trim_start_matches([' ', '\t']);
+        let Some(rest) = self.rest.strip_prefix('=') else {
+            return Err(format!("expected '=' after {key}"));
+        };
+        self.rest = rest.trim_start_matches([' ', '\t']);
+
+        let value = match self.rest.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let raw = self.quoted(quote)?;
+                let value = if quote == '"' { expand(raw, true, lookup) } else { raw.to_string() };
+                let trailing = self.current_line().trim();
+                if !trailing.is_empty() && !trailing.starts_with('#') {
+                    return Err(format!("unexpected '{trailing}' after the value of {key}"));
+                }
+                self.skip_line();
+                value
+            }
+            _ => {
+                let line = self.current_line();
+                self.skip_line();
+                // ` #` starts an inline comment, a `#` inside a word doesn't
+                let value = match line.find(" #").or_else(|| line.find("\t#")) {
+                    Some(i) => &line[..i],
+                    None => line,
+                };
+                expand(value.trim_end(), false, lookup)
+            }
+        };
+        Ok(Some((key.to_string(), value)))
+    }
+
+    /// The raw text of a quoted value starting at the opening quote, which may span lines
+    fn quoted(&mut self, quote: char) -> Result<&'a str, String> {
+        let body = &self.rest[1..];
+        let mut escaped = false;
+        for (i, c) in body.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quote == '"' => escaped = true,
+                c if c == quote => {
+                    let raw = &body[..i];
+                    self.line += raw.matches('\n').count();
+                    self.rest = &body[i + 1..];
+                    return Ok(raw);
+                }
+                _ => {}
+            }
+        }
+        self.rest = "";
+        Err("unterminated quoted value".into())
+    }
+
+    fn current_line(&self) -> &'a str {
+        self.rest.split('\n').next().unwrap_or("").trim_end_matches('\r')
+    }
+
+    fn skip_line(&mut self) {
+        match self.rest.find('\n') {
+            Some(i) => {
+                self.rest = &self.rest[i + 1..];
+                self.line += 1;
+            }
+            None => self.rest = "",
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        loop {
+            let trimmed = self.rest.trim_start_matches([' ', '\t', '\r']);
+            match trimmed.strip_prefix('\n') {
+                Some(rest) => {
+                    self.rest = rest;
+                    self.line += 1;
+                }
+                None => {
+                    self.rest = trimmed;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Expand `${NAME}`, `${NAME:-default}` and `$NAME`; a `$` not followed by a name is kept.
+/// With `escapes` (double-quoted values), `\n`, `\r`, `\t`, `\"`, `\\` and `\$` are unescaped too.
+fn expand(s: &str, escapes: bool, lookup: &dyn Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find(|c| c == '$' || (escapes && c == '\\')) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if let Some(after) = tail.strip_prefix('\\') {
+            let mut chars = after.chars();
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some(c @ ('"' | '\\' | '$')) => out.push(c),
+                // A backslash before a newline continues the line
+                Some('\n') => {}
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            }
+            rest = chars.as_str();
+        } else if let Some(body) = tail.strip_prefix("${") {
+            let Some(end) = body.find('}') else {
+                out.push_str(tail);
+                return out;
+            };
+            let (name, default) = match body[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&body[..end], None),
+            };
+            if is_name(name) {
+                let value = lookup(name);
+                match default {
+                    Some(default) if value.is_empty() => out.push_str(&expand(default, escapes, lookup)),
+                    _ => out.push_str(&value),
+                }
+            } else {
+                // Not a variable, e.g. pal's `${env:...}`, keep it as written
+                out.push_str(&tail[..end + 3]);
+            }
+            rest = &body[end + 1..];
+        } else {
+            let after = &tail[1..];
+            let len = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            if is_name(&after[..len]) {
+                out.push_str(&lookup(&after[..len]));
+            } else {
+                out.push('$');
+            }
+            rest = &after[len..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(content: &str) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        assert_eq!(parse(content, true, &[], &mut vars), vec![]);
+        vars
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn export_prefix() {
+        assert_eq!(vars("export A=1\nexport\tB=2\n"), pairs(&[("A", "1"), ("B", "2")]));
+    }
+
+    #[test]
+    fn comments() {
+        let content = "# comment\nA=one # inline\nB=x#y\nC='q' # after quotes\n";
+        assert_eq!(vars(content), pairs(&[("A", "one"), ("B", "x#y"), ("C", "q")]));
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        let content = "B=2\nS='$B ${B} \\n'\nD=\"$B ${B} \\n\"\n";
+        assert_eq!(vars(content), pairs(&[("B", "2"), ("S", "$B ${B} \\n"), ("D", "2 2 \n")]));
+    }
+
+    #[test]
+    fn double_quote_escapes() {
+        let content = r#"A="q\"b\\s\$d\tt\x""#;
+        assert_eq!(vars(content), pairs(&[("A", "q\"b\\s$d\tt\\x")]));
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let mut vars = Vec::new();
+        let errors = parse("A=1\nB=\"open\nC=3\n", true, &[], &mut vars);
+        assert_eq!(errors, vec![(2, "unterminated quoted value".to_string())]);
+        assert_eq!(vars, pairs(&[("A", "1")]));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped_with_their_line() {
+        let mut vars = Vec::new();
+        let errors = parse("A=\"multi\nline\"\n\nBAD LINE\n1X=2\nC=3\n", true, &[], &mut vars);
+        assert_eq!(errors, vec![
+            (4, "expected '=' after BAD".to_string()),
+            (5, "invalid variable name in '1X=2'".to_string()),
+        ]);
+        assert_eq!(vars, pairs(&[("A", "multi\nline"), ("C", "3")]));
+    }
+
+    #[test]
+    fn existing_variables_win_without_override() {
+        std::env::set_var("PAL_DOTENV_TEST", "env");
+        let content = "PAL_DOTENV_TEST=file\nB=${PAL_DOTENV_TEST}\n";
+
+        let mut vars = Vec::new();
+        parse(content, false, &[], &mut vars);
+        assert_eq!(vars.last(), Some(&("B".to_string(), "env".to_string())));
+        assert_eq!(lookup("PAL_DOTENV_TEST", &vars, false, &[]), "env");
+
+        let mut vars = Vec::new();
+        parse(content, true, &[], &mut vars);
+        assert_eq!(vars.last(), Some(&("B".to_string(), "file".to_string())));
+        assert_eq!(lookup("PAL_DOTENV_TEST", &vars, true, &[]), "file");
+    }
+}
@@ -1,24 +1,33 @@
+use std::sync::Arc;
+
 use crate::config::Frontend as FrontendConfig;
+use crate::context::{Context, Session};
 use crate::plugin::Plugin;
 
 pub struct Frontend {
     plugin: Plugin,
+    session: Arc<Session>,
 }
 
 impl Frontend {
-    pub fn new(base: &str, config: &FrontendConfig) -> Self {
-        Self { plugin: Plugin::new(base, config) }
+    pub fn new(base: &str, config: &FrontendConfig, session: &Arc<Session>) -> Self {
+        Self { plugin: Plugin::new(base, config), session: session.clone() }
     }
 
     pub fn run(&self, items: &str) -> String {
-        self.plugin.run("run", Some(items))
+        self.plugin.run_in("run", Some(items), Context::new(&self.session))
     }
 
     pub fn prompt(&self, message: &str) -> String {
-        self.plugin.run("prompt", Some(message))
+        self.plugin.run_in("prompt", Some(message), Context::new(&self.session))
     }
 
     pub fn input_run(&self, message: &str) -> String {
-        self.plugin.run("input_run", Some(message))
+        self.plugin.run_in("input_run", Some(message), Context::new(&self.session))
+    }
+
+    /// Whether the frontend runs in the invoking terminal (`tty = true` in plugin.toml or config)
+    pub fn has_tty(&self) -> bool {
+        self.plugin.config().get("tty").and_then(|v| v.as_bool()).unwrap_or(false)
     }
 }
@@ -0,0 +1,177 @@
+//! `${env:NAME}`, `${env:NAME:-default}`, `${env:NAME-default}`, `${var:name}` and
+//! `${cmd:command}` references in config values. They're resolved when a value is used, not
+//! when the config loads, so a secret behind `${cmd:pass show ...}` is only fetched by the
+//! palette that needs it.
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use crate::context::Session;
+
+/// Nested `${var:...}` lookups deeper than this are treated as a cycle
+const MAX_DEPTH: usize = 16;
+
+static VARS: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+/// Command output cache, so a plugin running `list` then `pick` asks for a secret once
+static COMMANDS: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+/// Install the `[vars]` table `${var:name}` refers to
+pub fn set_vars(vars: HashMap<String, String>) {
+    *VARS.lock().unwrap() = Some(vars);
+}
+
+/// Resolve every reference in a string. `$${` stands for a literal `${`.
+/// `${env:...}` sees the variables of `session` as well as pal's own.
+pub fn resolve(s: &str, session: &Session) -> Result<String, String> {
+    resolve_nested(s, session, 0)
+}
+
+/// Resolve references in every string of a JSON value, keys excluded
+pub fn resolve_json(value: &serde_json::Value, session: &Session) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::String(s) => Value::String(resolve(s, session)?),
+        Value::Array(a) => Value::Array(a.iter().map(|v| resolve_json(v, session)).collect::<Result<_, _>>()?),
+        Value::Object(o) => Value::Object(
+            o.iter()
+                .map(|(k, v)| Ok((k.clone(), resolve_json(v, session)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn resolve_nested(s: &str, session: &Session, depth: usize) -> Result<String, String> {
+    if !s.contains("${") {
+        return Ok(s.to_string());
+    }
+    if depth > MAX_DEPTH {
+        return Err(format!("too many nested ${{var:...}} references in '{s}'"));
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let body_start = start + 2;
+        let Some(len) = closing_brace(&rest[body_start..]) else {
+            return Err(format!("unclosed '${{' in '{s}'"));
+        };
+        let body = &rest[body_start..body_start + len];
+        match reference(body, session, depth)? {
+            Some(value) => out.push_str(&value),
+            // Not ours (e.g. a shell `${HOME}`), keep it as written
+            None => out.push_str(&rest[start..body_start + len + 1]),
+        }
+        rest = &rest[body_start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Offset of the `}` closing a reference body, skipping over nested `{...}`
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of a single reference body, or None for anything that isn't `env:`, `var:` or `cmd:`
+fn reference(body: &str, session: &Session, depth: usize) -> Result<Option<String>, String> {
+    let Some((kind, arg)) = body.split_once(':') else { return Ok(None) };
+    let value = match kind {
+        "env" => {
+            // `:-` falls back when the variable is unset or empty, `-` only when it's unset
+            let end = arg.find([':', '-']).unwrap_or(arg.len());
+            let (name, default, or_empty) = match (&arg[..end], &arg[end..]) {
+                (name, rest) if rest.starts_with(":-") => (name, Some(&rest[2..]), true),
+                (name, rest) if rest.starts_with('-') => (name, Some(&rest[1..]), false),
+                _ => (arg, None, false),
+            };
+            match (session.var(name), default) {
+                (Some(value), Some(default)) if or_empty && value.is_empty() => resolve_nested(default, session, depth + 1)?,
+                (Some(value), _) => value,
+                (None, Some(default)) => resolve_nested(default, session, depth + 1)?,
+                (None, None) => return Err(format!("environment variable {name} is not set")),
+            }
+        }
+        "var" => {
+            let value = VARS
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|vars| vars.get(arg).cloned())
+                .ok_or_else(|| format!("unknown var '{arg}', define it under [vars]"))?;
+            resolve_nested(&value, session, depth + 1)?
+        }
+        "cmd" => {
+            let command = resolve_nested(arg, session, depth + 1)?;
+            run(&command)?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Output of a shell command with trailing newlines trimmed, cached for the process
+fn run(command: &str) -> Result<String, String> {
+    if let Some(output) = COMMANDS.lock().unwrap().as_ref().and_then(|c| c.get(command)) {
+        return Ok(output.clone());
+    }
+
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("failed to run '{command}': {e}"))?;
+    if !output.status.success() {
+        return Err(format!("'{command}' exited with {}", output.status.code().unwrap_or(-1)));
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim_end_matches(['\n', '\r']).to_string();
+
+    COMMANDS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(command.to_string(), value.clone());
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(s: &str) -> Result<String, String> {
+        super::resolve(s, &Session::default())
+    }
+
+    #[test]
+    fn env_defaults() {
+        std::env::set_var("PAL_INTERPOLATE_EMPTY", "");
+        std::env::set_var("PAL_INTERPOLATE_SET", "value");
+        std::env::remove_var("PAL_INTERPOLATE_UNSET");
+
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_EMPTY:-d}").unwrap(), "d");
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_UNSET:-d}").unwrap(), "d");
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_SET:-d}").unwrap(), "value");
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_EMPTY-d}").unwrap(), "");
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_UNSET-d}").unwrap(), "d");
+        assert_eq!(resolve("${env:PAL_INTERPOLATE_UNSET:-a-b}").unwrap(), "a-b");
+        assert!(resolve("${env:PAL_INTERPOLATE_UNSET}").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+const HEADER: &str = "# Generated by pal - pins remote plugin sources to commits.\n# Refresh with `pal update [--plugin <name>]`.\n\n";
+
+/// Config file given with `--config`, which keeps its own pal.lock next to it
+static CONFIG: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Keep the lock next to `path` instead of the user config, so a project config
+/// gets a lock of its own to commit
+pub fn set_config(path: &Path) {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    *CONFIG.lock().unwrap() = Some(path);
+}
+
+/// `pal.lock` next to the config, recording the resolved commit of every remote repo
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub repos: BTreeMap<String, LockedRepo>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LockedRepo {
+    pub url: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub commit: String,
+    /// Config sources (bases and data paths) resolved from this repo
+    #[serde(default)]
+    pub sources: BTreeSet<String>,
+}
+
+impl Lockfile {
+    pub fn path() -> PathBuf {
+        if let Some(dir) = CONFIG.lock().unwrap().as_ref().and_then(|c| c.parent()) {
+            return dir.join("pal.lock");
+        }
+        dirs::config_dir()
+            .map(|p| p.join("pal/pal.lock"))
+            .unwrap_or_else(|| PathBuf::from("pal.lock"))
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|s| toml::from_str(&s).map_err(|e| eprintln!("pal.lock: {e}")).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let path = Self::path();
+        let content = match toml::to_string_pretty(self) {
+            Ok(s) => format!("{HEADER}{s}"),
+            Err(e) => {
+                eprintln!("failed to serialize pal.lock: {e}");
+                return;
+            }
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        if let Err(e) = std::fs::write(&path, content) {
+            eprintln!("failed to write {}: {e}", path.display());
+        }
+    }
+
+    /// Commit pinned for a repo key, if any
+    pub fn commit(&self, key: &str) -> Option<&str> {
+        self.repos.get(key).map(|r| r.commit.as_str())
+    }
+
+    /// Record a source for a repo, returning true if the lockfile changed
+    pub fn add_source(&mut self, key: &str, source: &str) -> bool {
+        match self.repos.get_mut(key) {
+            Some(repo) => repo.sources.insert(source.to_string()),
+            None => false,
+        }
+    }
+
+    /// Set the pinned commit for a repo, keeping its recorded sources
+    pub fn set_commit(&mut self, key: &str, url: &str, git_ref: &str, commit: &str) {
+        let entry = self.repos.entry(key.to_string()).or_insert_with(|| LockedRepo {
+            url: url.to_string(),
+            git_ref: git_ref.to_string(),
+            commit: String::new(),
+            sources: BTreeSet::new(),
+        });
+        entry.url = url.to_string();
+        entry.commit = commit.to_string();
+    }
+}
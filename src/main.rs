@@ -1,20 +1,32 @@
 mod action;
 mod builtin;
 mod config;
+mod config_cmd;
+mod context;
+mod conformance;
+mod dotenv;
 mod frontend;
+mod interpolate;
+mod lock;
 mod palette;
 mod plugin;
+mod plugins;
 mod remote;
+mod scaffold;
+mod schema;
+mod trust;
 mod util;
 
 use std::process;
+use std::sync::Arc;
 
 use clap::Parser;
 use config::Config;
+use context::{Context, Session};
 use frontend::Frontend;
 use palette::Palette;
 
-#[derive(Parser, Default)]
+#[derive(Parser)]
 #[command(name = "pal", about = "pal - palette tool", version)]
 pub struct Cli {
     /// Path to config file
@@ -25,15 +37,30 @@ pub struct Cli {
     #[arg(short, long)]
     pub log_level: Option<String>,
 
+    /// Never touch git for remote plugins; missing plugins are errors
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Config profile to apply (default: PAL_PROFILE or a profile matching the hostname)
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Log plugin output that doesn't match the item schema instead of dropping it silently
+    #[arg(long)]
+    pub strict: bool,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
 
-impl Cli {
-    pub fn default() -> Self {
+impl Default for Cli {
+    fn default() -> Self {
         Self {
             config: "pal.default.toml".into(),
             log_level: None,
+            offline: false,
+            strict: false,
+            profile: None,
             command: None,
         }
     }
@@ -47,8 +74,19 @@ pub enum Command {
         #[arg(short, long)]
         force: bool,
     },
-    /// Show loaded configuration
-    ShowConfig,
+    /// Show the effective configuration and where each value came from
+    ShowConfig {
+        /// Dotted key to show, e.g. `general` or `palette.audio`
+        key: Option<String>,
+        /// Output format; TOML notes each value's source in a comment
+        #[arg(long, value_enum, default_value = "toml")]
+        format: config_cmd::Format,
+    },
+    /// Read or edit config values, keeping the file's comments and layout
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
     /// Run with optional frontend and palette
     Run {
         /// Frontend to use (default from config)
@@ -63,13 +101,62 @@ pub enum Command {
     },
     /// Run an action (reads value from stdin)
     Action {
-        /// Action name (e.g. copy, open, cmd)
+        /// Action names (e.g. copy, open, cmd); several run as a chain
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Manage remote plugins (lists installed plugins without a subcommand)
+    #[command(alias = "plugin")]
+    Plugins {
+        #[command(subcommand)]
+        command: Option<PluginsCommand>,
+    },
+    /// Update remote plugins and refresh pal.lock
+    Update {
+        /// Only update this plugin (name, source or repo)
+        #[arg(long)]
+        plugin: Option<String>,
+        /// Only fetch and report what would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the JSON Schema of items, plugin.toml or the config
+    Schema {
+        #[arg(value_enum)]
+        target: schema::Target,
+    },
+    /// Create a new plugin from a template
+    New {
+        kind: scaffold::Kind,
         name: String,
+        /// Language of the entrypoint
+        #[arg(long, value_enum, default_value = "bash")]
+        lang: scaffold::Lang,
+        /// Palette takes a query from the frontend (input = true)
+        #[arg(long)]
+        input: bool,
+        /// Palette lists items from a sample data.json
+        #[arg(long)]
+        data: bool,
+        /// Directory to create (default: ~/.config/pal/plugins/<kind>s/<name>)
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+        /// Add the plugin to the user config
+        #[arg(long)]
+        register: bool,
+    },
+    /// Trust a remote plugin repo (lists trusted repos without an argument)
+    Trust {
+        /// Source like github:user/repo or a plugin base
+        source: Option<String>,
+    },
+    /// Remove a remote plugin repo from the trust store
+    Untrust {
+        source: String,
     },
-    /// List installed remote plugins
-    Plugins,
-    /// Update all remote plugins
-    Update,
     /// Regenerate cache for a palette+frontend (internal)
     #[command(hide = true)]
     CacheRegen {
@@ -106,14 +193,157 @@ pub enum Command {
     },
 }
 
+#[derive(clap::Subcommand)]
+pub enum ConfigCommand {
+    /// Print a value of the effective config, or of one file with --file
+    Get {
+        /// Dotted key, e.g. general.default_frontend
+        key: String,
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+    },
+    /// Set a value in the user config (or --file)
+    Set {
+        /// Dotted key, e.g. palette.apps.cache
+        key: String,
+        /// TOML value like `true` or `["a", "b"]`; anything else is stored as a string
+        value: String,
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+    },
+    /// Remove a value from the user config (or --file)
+    Unset {
+        key: String,
+        #[arg(long)]
+        file: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum PluginsCommand {
+    /// List installed remote plugins and check them for updates
+    List {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Install a remote plugin and add it as a palette to the user config
+    Add {
+        /// Source like github:user/repo/path[@ref]
+        source: String,
+        /// Palette name (defaults to the plugin name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Remove a palette from the user config and prune its unused clone
+    Remove {
+        name: String,
+        /// Delete the clone without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Show source, pinned commit, plugin.toml fields and requirements of a palette
+    Info {
+        palette: String,
+    },
+    /// Delete clones and sparse paths no longer referenced by the config
+    Prune {
+        /// Delete without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Check a plugin directory against the plugin contract
+    Test {
+        dir: std::path::PathBuf,
+        /// Plugin kind (guessed from the directory and plugin.toml by default)
+        #[arg(long)]
+        kind: Option<scaffold::Kind>,
+        /// Query sent to `list` of input palettes
+        #[arg(long, default_value = "a")]
+        query: String,
+        /// Seconds to wait for each plugin command
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+}
+
 fn main() {
     let cli = Cli::parse();
+    // What a parent pal passed down, plus this invocation's flags
+    let mut session = Session::inherited();
+    session.offline |= cli.offline;
+    session.strict |= cli.strict;
+    if session.offline {
+        remote::set_offline();
+    }
+    // Same check as Config::load: an explicit --config keeps its own pal.lock.
+    // remove and prune work on the user config only, and so on the user lock.
+    let user_scoped = matches!(
+        cli.command,
+        Some(Command::Plugins { command: Some(PluginsCommand::Remove { .. } | PluginsCommand::Prune { .. }) })
+    );
+    if !cli.config.ends_with("pal.default.toml") && !user_scoped {
+        lock::set_config(&util::expand_path(&cli.config));
+    }
 
     // Handle commands that don't need config
     match &cli.command {
         Some(Command::Init { force }) => { init_config(*force); return; }
-        Some(Command::Plugins) => { remote::list_plugins(); return; }
-        Some(Command::Update) => { remote::update_plugins(); return; }
+        Some(Command::Schema { target }) => { schema::print(*target); return; }
+        Some(Command::Plugins { command: None }) => { remote::list_plugins(false); return; }
+        Some(Command::Plugins { command: Some(PluginsCommand::List { json }) }) => { remote::list_plugins(*json); return; }
+        Some(Command::Plugins { command: Some(PluginsCommand::Add { source, name }) }) => {
+            plugins::add(source, name.as_deref());
+            return;
+        }
+        Some(Command::Update { plugin, dry_run, json }) => {
+            remote::update_plugins(plugin.as_deref(), *dry_run, *json);
+            return;
+        }
+        Some(Command::Plugins { command: Some(PluginsCommand::Remove { name, yes }) }) => {
+            plugins::remove(&mut user_config(&cli), name, *yes);
+            return;
+        }
+        Some(Command::Plugins { command: Some(PluginsCommand::Prune { yes }) }) => {
+            plugins::prune(&user_config(&cli), *yes);
+            return;
+        }
+        Some(Command::Plugins { command: Some(PluginsCommand::Test { dir, kind, query, timeout }) }) => {
+            conformance::run(&conformance::Options {
+                dir: dir.clone(),
+                kind: *kind,
+                query: query.clone(),
+                timeout: std::time::Duration::from_secs(*timeout),
+            });
+            return;
+        }
+        Some(Command::New { kind, name, lang, input, data, dir, register }) => {
+            scaffold::new_plugin(&scaffold::Options {
+                kind: *kind,
+                name: name.clone(),
+                lang: *lang,
+                input: *input,
+                data: *data,
+                dir: dir.clone(),
+                register: *register,
+            });
+            return;
+        }
+        Some(Command::Trust { source: Some(source) }) => { remote::trust(source); return; }
+        Some(Command::Trust { source: None }) => { remote::list_trusted(); return; }
+        Some(Command::Untrust { source }) => { remote::untrust(source); return; }
+        Some(Command::Config { command: ConfigCommand::Get { key, file: Some(file) } }) => {
+            config_cmd::get_file(file, key);
+            return;
+        }
+        Some(Command::Config { command: ConfigCommand::Set { key, value, file } }) => {
+            config_cmd::set(file.as_deref(), key, value);
+            return;
+        }
+        Some(Command::Config { command: ConfigCommand::Unset { key, file } }) => {
+            config_cmd::unset(file.as_deref(), key);
+            return;
+        }
         _ => {}
     }
 
@@ -124,7 +354,7 @@ fn main() {
     let cfg = Config::load(&config_str, &cli);
 
     match cfg {
-        Ok(cfg) => dispatch(&config_str, cli.command, cfg),
+        Ok(cfg) => dispatch(&config_str, cli.command, cfg, session),
         Err(e) => eprintln!("config error: {e}"),
     }
 }
@@ -159,90 +389,116 @@ fn init_config(force: bool) {
     println!("created {}", config_path.display());
 }
 
-fn load_env_file(path: &str) {
-    let path = util::expand_path(path);
-    let Ok(content) = std::fs::read_to_string(&path) else { return };
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') { continue; }
-        if let Some((key, val)) = line.split_once('=') {
-            std::env::set_var(key.trim(), val.trim_matches('"').trim_matches('\''));
-        }
+fn dispatch(config_path: &str, command: Option<Command>, mut cfg: Config, mut session: Session) {
+    session.config = Some(config_path.into());
+    session.profile = cfg.active_profile.clone();
+
+    if cfg.general.offline {
+        session.offline = true;
+        remote::set_offline();
     }
-}
 
-fn dispatch(config_path: &str, command: Option<Command>, cfg: Config) {
-    std::env::set_var("_PAL_CONFIG", config_path);
-    if let Some(parent) = std::path::Path::new(config_path).parent() {
-        std::env::set_var("_PAL_CONFIG_DIR", parent);
+    // Only the palette that's actually used gets its remote plugin fetched
+    if let Some(name) = used_palette(command.as_ref(), &cfg, &session) {
+        cfg.resolve_palette(&name).expect_exit("config error");
     }
 
-    if let Some(ref env_file) = cfg.general.env_file {
-        load_env_file(env_file);
+    if let Some(ref terminal) = cfg.general.terminal {
+        session.terminal = Some(terminal.clone());
     }
+    session.env = dotenv::read(&cfg.general.env_file.0, cfg.general.env_override, &[]);
+    let session = Arc::new(session);
 
     match command {
-        Some(Command::Init { .. } | Command::Plugins | Command::Update) => unreachable!(),
+        Some(Command::Init { .. }
+            | Command::Plugins { command: None | Some(PluginsCommand::List { .. } | PluginsCommand::Add { .. } | PluginsCommand::Remove { .. } | PluginsCommand::Prune { .. } | PluginsCommand::Test { .. }) }
+            | Command::Update { .. }
+            | Command::New { .. }
+            | Command::Schema { .. } | Command::Trust { .. } | Command::Untrust { .. }
+            | Command::Config { command: ConfigCommand::Get { file: Some(_), .. } | ConfigCommand::Set { .. } | ConfigCommand::Unset { .. } }) => unreachable!(),
         Some(Command::CacheRegen { palette, frontend }) => {
-            regen_cache(&cfg, &palette, &frontend);
+            regen_cache(&cfg, &palette, &frontend, &session);
         }
         Some(Command::InputList { palette, frontend }) => {
-            input_list(&cfg, &palette, &frontend);
+            input_list(&cfg, &palette, &frontend, &session);
         }
         Some(Command::RofiInput { palette, selected }) => {
-            rofi_input(&cfg, &palette, selected.as_deref());
+            rofi_input(&cfg, &palette, selected.as_deref(), &session);
         }
         Some(Command::RofiBlocksInput { palette }) => {
-            rofi_blocks_input(&cfg, &palette);
+            rofi_blocks_input(&cfg, &palette, &session);
         }
         Some(Command::Select) => {
             use std::io::Read;
             let mut items = String::new();
             std::io::stdin().read_to_string(&mut items).ok();
-            let fe_name = std::env::var("_PAL_FRONTEND").unwrap_or(cfg.general.default_frontend.clone());
+            let fe_name = session.frontend.clone().unwrap_or(cfg.general.default_frontend.clone());
             let frontend_cfg = cfg.frontend.get(&fe_name).expect_exit(&format!("frontend not found: {fe_name}"));
-            if let Some(selected) = select(frontend_cfg, items.trim_end()) {
+            if let Some(selected) = select(frontend_cfg, items.trim_end(), &session) {
                 print!("{selected}");
             }
         }
         Some(Command::Prompt { frontend, spec }) => {
-            prompt_cmd(&cfg, spec.as_deref(), frontend.as_deref());
+            prompt_cmd(&cfg, spec.as_deref(), frontend.as_deref(), &session);
         }
-        Some(Command::ShowConfig) => println!("{cfg:#?}"),
-        Some(Command::Run { frontend, palette }) => run(&cfg, frontend.as_deref(), palette.as_deref()),
+        Some(Command::ShowConfig { key, format }) => config_cmd::show(&cfg, key.as_deref(), format),
+        Some(Command::Config { command: ConfigCommand::Get { key, file: None } }) => config_cmd::get(&cfg, &key),
+        Some(Command::Plugins { command: Some(PluginsCommand::Info { palette }) }) => plugins::info(&cfg, &palette),
+        Some(Command::Run { frontend, palette }) => run(&cfg, frontend.as_deref(), palette.as_deref(), &session),
         Some(Command::List { palette }) => {
             let palette_name = palette.as_deref().unwrap_or(&cfg.general.default_palette);
             let palette_cfg = cfg.palette.get(palette_name).expect_exit(&format!("palette not found: {palette_name}"));
-            print!("{}", list(palette_cfg, None));
+            print!("{}", Palette::new(&cfg, palette_cfg, &session).list(None));
         }
-        Some(Command::Action { name }) => {
+        Some(Command::Action { names }) => {
             use std::io::Read;
             let mut value = String::new();
             std::io::stdin().read_to_string(&mut value).ok();
-            print!("{}", action::Action::new(&name).run(value.trim_end()));
+            let chain: Vec<_> = names.into_iter().map(config::ActionRef::Name).collect();
+            print!("{}", action::run_chain(&chain, &cfg.action, value.trim_end(), &Context::new(&session)).expect_exit("action failed"));
         }
-        None => run(&cfg, None, None),
+        None => run(&cfg, None, None, &session),
+    }
+}
+
+/// Name of the palette a command is going to use, if any
+fn used_palette(command: Option<&Command>, cfg: &Config, session: &Session) -> Option<String> {
+    let palette_env = session.palette.clone();
+    let default = &cfg.general.default_palette;
+    match command {
+        Some(Command::Run { palette, .. }) => Some(palette.clone().or(palette_env).unwrap_or_else(|| default.clone())),
+        Some(Command::List { palette }) => Some(palette.clone().unwrap_or_else(|| default.clone())),
+        Some(Command::CacheRegen { palette, .. }
+            | Command::InputList { palette, .. }
+            | Command::RofiInput { palette, .. }
+            | Command::RofiBlocksInput { palette }) => Some(palette.clone()),
+        None => Some(palette_env.unwrap_or_else(|| default.clone())),
+        _ => None,
     }
 }
 
-fn run(cfg: &Config, frontend_arg: Option<&str>, palette_arg: Option<&str>) {
-    let palette_env = std::env::var("_PAL_PALETTE").ok();
+fn run(cfg: &Config, frontend_arg: Option<&str>, palette_arg: Option<&str>, session: &Arc<Session>) {
     let palette_name = palette_arg
-        .or(palette_env.as_deref())
+        .or(session.palette.as_deref())
         .unwrap_or(&cfg.general.default_palette);
     let palette_cfg = cfg.palette.get(palette_name).expect_exit(&format!("palette not found: {palette_name}"));
 
-    let frontend_env = std::env::var("_PAL_FRONTEND").ok();
     let frontend_name = frontend_arg
-        .or(frontend_env.as_deref())
+        .or(session.frontend.as_deref())
         .unwrap_or(&cfg.general.default_frontend);
     let frontend_cfg = cfg.frontend.get(frontend_name).expect_exit(&format!("frontend not found: {frontend_name}"));
 
-    std::env::set_var("_PAL_PALETTE", palette_name);
-    std::env::set_var("_PAL_FRONTEND", frontend_name);
+    let session = Arc::new(Session {
+        palette: Some(palette_name.to_string()),
+        frontend: Some(frontend_name.to_string()),
+        tty: Some(frontend_tty(frontend_cfg, session)),
+        ..Session::clone(session)
+    });
+    set_trust_prompter(frontend_cfg, &session);
+    let palette = Palette::new(cfg, palette_cfg, &session);
 
     if palette_cfg.cache && frontend_cfg.base.as_deref() == Some("builtin/frontends/rofi") {
-        run_cached_rofi(cfg, palette_name, palette_cfg, frontend_name);
+        run_cached_rofi(cfg, palette_name, &palette, frontend_name, &session);
         return;
     }
 
@@ -250,42 +506,71 @@ fn run(cfg: &Config, frontend_arg: Option<&str>, palette_arg: Option<&str>) {
         let base = frontend_cfg.base.as_ref().expect_exit("frontend has no base");
         let msg = palette_cfg.input_prompt.as_deref().unwrap_or(palette_name);
         if base == "builtin/frontends/fzf" {
-            let fe = Frontend::new(base, frontend_cfg);
+            let fe = Frontend::new(base, frontend_cfg, &session);
             let sel = fe.input_run(msg);
-            if !sel.trim().is_empty() { resolve_and_pick(cfg, palette_cfg, &sel, Some(frontend_name)); }
+            if !sel.trim().is_empty() { resolve_and_pick(cfg, &palette, &sel, Some(frontend_name), &session); }
         } else if base == "builtin/frontends/rofi" {
             if palette_cfg.live && builtin::rofi::has_blocks() {
-                builtin::rofi::blocks_input_run(msg);
+                builtin::rofi::blocks_input_run(msg, &Context::new(&session));
             } else {
                 if palette_cfg.live {
                     eprintln!("pal: rofi-blocks not found, falling back to script mode for live palette '{palette_name}'");
                 }
-                Frontend::new(base, frontend_cfg).input_run(msg);
+                Frontend::new(base, frontend_cfg, &session).input_run(msg);
             }
         } else {
-            let fe = Frontend::new(base, frontend_cfg);
+            let fe = Frontend::new(base, frontend_cfg, &session);
             let q = fe.prompt(msg);
             if q.is_empty() { return; }
-            let items = list(palette_cfg, Some(&q));
-            if let Some(selected) = select(frontend_cfg, &items) {
-                resolve_and_pick(cfg, palette_cfg, &selected, Some(frontend_name));
+            let items = palette.list(Some(&q));
+            if let Some(selected) = select(frontend_cfg, &items, &session) {
+                resolve_and_pick(cfg, &palette, &selected, Some(frontend_name), &session);
             }
         }
         return;
     }
 
-    let items = list(palette_cfg, None);
-    let selected = select(frontend_cfg, &items);
+    let items = palette.list(None);
+    let selected = select(frontend_cfg, &items, &session);
     if let Some(selected) = selected {
-        resolve_and_pick(cfg, palette_cfg, &selected, Some(frontend_name));
+        resolve_and_pick(cfg, &palette, &selected, Some(frontend_name), &session);
     }
 }
 
+/// Whether picked items can use the current terminal (`_PAL_TTY`)
+fn frontend_tty(frontend_cfg: &config::Frontend, session: &Arc<Session>) -> bool {
+    use std::io::IsTerminal;
+    let owns_tty = frontend_cfg.base.as_deref()
+        .is_some_and(|base| Frontend::new(base, frontend_cfg, session).has_tty());
+    owns_tty && std::io::stdin().is_terminal()
+}
+
+/// Ask trust questions for remote plugins through the active frontend
+fn set_trust_prompter(frontend_cfg: &config::Frontend, session: &Arc<Session>) {
+    let Some(base) = frontend_cfg.base.as_deref() else { return };
+    let fe = Frontend::new(base, frontend_cfg, session);
+    trust::set_prompter(Box::new(move |message, details| {
+        let items = [
+            serde_json::json!({"id": "yes", "name": format!("Yes - {message}")}),
+            serde_json::json!({"id": "no", "name": "No, cancel"}),
+        ]
+        .into_iter()
+        .chain(details.iter().map(|d| serde_json::json!({"id": "no", "name": d})))
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+        let sel = fe.run(&items);
+        serde_json::from_str::<serde_json::Value>(&sel).ok()
+            .is_some_and(|v| v.get("id").and_then(|v| v.as_str()) == Some("yes"))
+    }));
+}
+
 fn cache_dir() -> std::path::PathBuf {
     dirs::cache_dir().unwrap_or_default().join("pal")
 }
 
-fn run_cached_rofi(cfg: &Config, palette_name: &str, palette_cfg: &config::Palette, frontend_name: &str) {
+fn run_cached_rofi(cfg: &Config, palette_name: &str, palette: &Palette, frontend_name: &str, session: &Arc<Session>) {
+    let ctx = Context::new(session);
     let dir = cache_dir();
     let display_path = dir.join(format!("{palette_name}.rofi.display"));
     let items_path = dir.join(format!("{palette_name}.rofi.items"));
@@ -294,30 +579,29 @@ fn run_cached_rofi(cfg: &Config, palette_name: &str, palette_cfg: &config::Palet
         let display = std::fs::read_to_string(&display_path).unwrap_or_default();
         let items_str = std::fs::read_to_string(&items_path).unwrap_or_default();
         let raw_items: Vec<String> = items_str.lines().map(String::from).collect();
-        let sel = builtin::rofi::pick_display(&display, &raw_items);
-        spawn_cache_regen(palette_name);
+        let sel = builtin::rofi::pick_display(&display, &raw_items, &ctx);
+        spawn_cache_regen(palette_name, &ctx);
         if sel.trim().is_empty() { None } else { Some(sel) }
     } else {
         // No cache yet - generate, cache, then display
-        let items = list(palette_cfg, None);
+        let items = palette.list(None);
         let (display, raw_items) = builtin::rofi::format_items(&items);
         std::fs::create_dir_all(&dir).ok();
         std::fs::write(&display_path, &display).ok();
         std::fs::write(&items_path, &items).ok();
-        let sel = builtin::rofi::pick_display(&display, &raw_items);
+        let sel = builtin::rofi::pick_display(&display, &raw_items, &ctx);
         if sel.trim().is_empty() { None } else { Some(sel) }
     };
 
     if let Some(selected) = selected {
-        resolve_and_pick(cfg, palette_cfg, &selected, Some(frontend_name));
+        resolve_and_pick(cfg, palette, &selected, Some(frontend_name), session);
     }
 }
 
-fn spawn_cache_regen(palette_name: &str) {
-    let config_path = std::env::var("_PAL_CONFIG").unwrap_or_else(|_| "pal.default.toml".into());
+fn spawn_cache_regen(palette_name: &str, ctx: &Context) {
     let exe = std::env::current_exe().unwrap_or_else(|_| "pal".into());
-    std::process::Command::new(exe)
-        .args(["--config", &config_path, "cache-regen", palette_name, "rofi"])
+    ctx.command(exe)
+        .args(["--config", &ctx.session.config_file(), "cache-regen", palette_name, "rofi"])
         .stdin(process::Stdio::null())
         .stdout(process::Stdio::null())
         .stderr(process::Stdio::null())
@@ -325,9 +609,9 @@ fn spawn_cache_regen(palette_name: &str) {
         .ok();
 }
 
-fn regen_cache(cfg: &Config, palette_name: &str, frontend_name: &str) {
+fn regen_cache(cfg: &Config, palette_name: &str, frontend_name: &str, session: &Arc<Session>) {
     let Some(palette_cfg) = cfg.palette.get(palette_name) else { return };
-    let items = list(palette_cfg, None);
+    let items = Palette::new(cfg, palette_cfg, session).list(None);
     let dir = cache_dir();
     std::fs::create_dir_all(&dir).ok();
 
@@ -338,14 +622,13 @@ fn regen_cache(cfg: &Config, palette_name: &str, frontend_name: &str) {
     }
 }
 
-fn rofi_input(cfg: &Config, palette_name: &str, selected: Option<&str>) {
+fn rofi_input(cfg: &Config, palette_name: &str, selected: Option<&str>, session: &Session) {
     let Some(palette_cfg) = cfg.palette.get(palette_name) else { return };
     let retv = std::env::var("ROFI_RETV").unwrap_or_default();
     let info = std::env::var("ROFI_INFO").ok();
     let msg = palette_cfg.input_prompt.as_deref().unwrap_or(palette_name);
-
-    std::env::set_var("_PAL_PALETTE", palette_name);
-    std::env::set_var("_PAL_FRONTEND", "rofi");
+    let session = rofi_session(session, palette_name);
+    let palette = Palette::new(cfg, palette_cfg, &session);
 
     match retv.as_str() {
         "" | "0" => {
@@ -356,7 +639,7 @@ fn rofi_input(cfg: &Config, palette_name: &str, selected: Option<&str>) {
             // Custom entry - user typed a query
             let query = selected.unwrap_or("");
             if query.is_empty() { return; }
-            let items = list(palette_cfg, Some(query));
+            let items = palette.list(Some(query));
             let formatted = builtin::rofi::format_script_items(&items);
             print!("\0prompt\x1f{msg}> \x1fmarkup-rows\x1ftrue\x1fkeep-filter\x1ffalse");
             if !formatted.is_empty() {
@@ -366,9 +649,9 @@ fn rofi_input(cfg: &Config, palette_name: &str, selected: Option<&str>) {
         "1" => {
             // Selected an entry - resolve prompts then pick
             if let Some(json) = info {
-                let resolved = resolve_prompts(&json, cfg, Some("rofi"));
+                let resolved = resolve_prompts(&json, cfg, Some("rofi"), &session);
                 if let Some(resolved) = resolved {
-                    let _ = Palette::new(palette_cfg).pick(&resolved);
+                    let _ = palette.pick(&resolved);
                 }
             }
         }
@@ -376,12 +659,11 @@ fn rofi_input(cfg: &Config, palette_name: &str, selected: Option<&str>) {
     }
 }
 
-fn rofi_blocks_input(cfg: &Config, palette_name: &str) {
+fn rofi_blocks_input(cfg: &Config, palette_name: &str, session: &Session) {
     let Some(palette_cfg) = cfg.palette.get(palette_name) else { return };
     let msg = palette_cfg.input_prompt.as_deref().unwrap_or(palette_name);
-
-    std::env::set_var("_PAL_PALETTE", palette_name);
-    std::env::set_var("_PAL_FRONTEND", "rofi");
+    let session = rofi_session(session, palette_name);
+    let palette = Palette::new(cfg, palette_cfg, &session);
 
     // Initial output: enable input events, set prompt, empty list
     println!("{}", serde_json::json!({
@@ -407,7 +689,7 @@ fn rofi_blocks_input(cfg: &Config, palette_name: &str) {
                     println!("{}", serde_json::json!({"lines": []}));
                     continue;
                 }
-                let items = list(palette_cfg, Some(value));
+                let items = palette.list(Some(value));
                 let lines = builtin::rofi::format_blocks_lines(&items);
                 let count = lines.len();
                 let msg = if count == 1 { "1 result".to_string() } else { format!("{count} results") };
@@ -415,9 +697,9 @@ fn rofi_blocks_input(cfg: &Config, palette_name: &str) {
             }
             "select entry" => {
                 if !data.is_empty() {
-                    let resolved = resolve_prompts(data, cfg, Some("rofi"));
+                    let resolved = resolve_prompts(data, cfg, Some("rofi"), &session);
                     if let Some(resolved) = resolved {
-                        let _ = Palette::new(palette_cfg).pick(&resolved);
+                        let _ = palette.pick(&resolved);
                     }
                 }
                 break;
@@ -427,14 +709,24 @@ fn rofi_blocks_input(cfg: &Config, palette_name: &str) {
     }
 }
 
-fn input_list(cfg: &Config, palette_name: &str, frontend_name: &str) {
+/// Session of a call from rofi's script or blocks mode, which runs without a terminal
+fn rofi_session(session: &Session, palette_name: &str) -> Arc<Session> {
+    Arc::new(Session {
+        palette: Some(palette_name.to_string()),
+        frontend: Some("rofi".into()),
+        tty: Some(false),
+        ..session.clone()
+    })
+}
+
+fn input_list(cfg: &Config, palette_name: &str, frontend_name: &str, session: &Arc<Session>) {
     use std::io::Read;
     let mut query = String::new();
     std::io::stdin().read_to_string(&mut query).ok();
     let query = query.trim_end();
 
     let Some(palette_cfg) = cfg.palette.get(palette_name) else { return };
-    let items = list(palette_cfg, if query.is_empty() { None } else { Some(query) });
+    let items = Palette::new(cfg, palette_cfg, session).list(if query.is_empty() { None } else { Some(query) });
 
     match frontend_name {
         "fzf" => print!("{}", builtin::fzf::format_items(&items)),
@@ -443,30 +735,34 @@ fn input_list(cfg: &Config, palette_name: &str, frontend_name: &str) {
     }
 }
 
-fn list(cfg: &config::Palette, query: Option<&str>) -> String {
-    Palette::new(cfg).list(query)
+/// The user config without project files or `--config`, for commands that manage it
+fn user_config(cli: &Cli) -> Config {
+    Config::load_user(cli).unwrap_or_else(|e| {
+        eprintln!("config error: {e}");
+        process::exit(1);
+    })
 }
 
-fn select(cfg: &config::Frontend, items: &str) -> Option<String> {
+fn select(cfg: &config::Frontend, items: &str, session: &Arc<Session>) -> Option<String> {
     let base = cfg.base.as_ref().expect_exit("frontend has no base");
-    let selected = Frontend::new(base, cfg).run(items);
+    let selected = Frontend::new(base, cfg, session).run(items);
     if selected.trim().is_empty() { None } else { Some(selected) }
 }
 
 /// Resolve item-level prompts then pick. If item has no prompts, picks directly.
-fn resolve_and_pick(full_cfg: &Config, palette_cfg: &config::Palette, selected: &str, frontend_name: Option<&str>) {
-    let resolved = match resolve_prompts(selected, full_cfg, frontend_name) {
+fn resolve_and_pick(full_cfg: &Config, palette: &Palette, selected: &str, frontend_name: Option<&str>, session: &Arc<Session>) {
+    let resolved = match resolve_prompts(selected, full_cfg, frontend_name, session) {
         Some(r) => r,
         None => return, // user cancelled a prompt
     };
-    let result = Palette::new(palette_cfg).pick(&resolved);
+    let result = palette.pick(&resolved);
     if !result.is_empty() {
         print!("{result}");
     }
 }
 
 /// If the item has a `prompts` array, run each prompt and substitute `{{key}}` in fields.
-fn resolve_prompts(selected: &str, cfg: &Config, frontend_name: Option<&str>) -> Option<String> {
+fn resolve_prompts(selected: &str, cfg: &Config, frontend_name: Option<&str>, session: &Arc<Session>) -> Option<String> {
     let mut item: serde_json::Value = serde_json::from_str(selected).ok()?;
 
     let prompts = match item.get("prompts") {
@@ -477,7 +773,7 @@ fn resolve_prompts(selected: &str, cfg: &Config, frontend_name: Option<&str>) ->
         return Some(selected.to_string());
     }
 
-    let values = run_prompts(&prompts, cfg, frontend_name)?;
+    let values = run_prompts(&prompts, cfg, frontend_name, session)?;
 
     // Remove prompts field
     if let Some(obj) = item.as_object_mut() {
@@ -485,11 +781,10 @@ fn resolve_prompts(selected: &str, cfg: &Config, frontend_name: Option<&str>) ->
     }
 
     // Substitute {{key}} in the serialized JSON string
-    let mut json_str = item.to_string();
-    for (key, value) in &values {
-        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
-        json_str = json_str.replace(&format!("{{{{{key}}}}}"), &escaped);
-    }
+    let escaped: Vec<_> = values.iter()
+        .map(|(key, value)| (key.clone(), value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    let json_str = fill_template(&item.to_string(), &escaped);
 
     // Re-parse and inject prompt values as fields (become PAL_<KEY> env vars)
     let mut item: serde_json::Value = serde_json::from_str(&json_str).ok()?;
@@ -506,28 +801,45 @@ fn resolve_prompts(selected: &str, cfg: &Config, frontend_name: Option<&str>) ->
 
 /// Core prompt runner - shared by resolve_prompts and `pal prompt`.
 /// Returns collected (key, value) pairs, or None if user cancelled.
-fn run_prompts(prompts: &[serde_json::Value], cfg: &Config, frontend_name: Option<&str>) -> Option<Vec<(String, String)>> {
-    // Determine frontend: explicit arg > _PAL_FRONTEND env > config default
-    let fe_name_env = std::env::var("_PAL_FRONTEND").ok();
+fn run_prompts(prompts: &[serde_json::Value], cfg: &Config, frontend_name: Option<&str>, session: &Arc<Session>) -> Option<Vec<(String, String)>> {
+    // Determine frontend: explicit arg > _PAL_FRONTEND of the session > config default
     let fe_name = frontend_name
-        .or(fe_name_env.as_deref())
+        .or(session.frontend.as_deref())
         .unwrap_or(&cfg.general.default_frontend);
     let frontend_cfg = cfg.frontend.get(fe_name)?;
     let base = frontend_cfg.base.as_ref()?;
-    let fe = Frontend::new(base, frontend_cfg);
+    let fe = Frontend::new(base, frontend_cfg, session);
 
     let mut values: Vec<(String, String)> = Vec::new();
 
     for prompt in prompts {
         let key = prompt.get("key").and_then(|v| v.as_str()).unwrap_or("");
-        let message = prompt.get("message").and_then(|v| v.as_str()).unwrap_or(key);
+        let default = prompt.get("default").and_then(|v| v.as_str()).map(|d| fill_template(d, &values));
+
+        // Skipped prompts resolve to their default so later {{key}} references stay valid
+        if let Some(cond) = prompt.get("when").and_then(|v| v.as_str()) {
+            if !eval_condition(cond, &values) {
+                values.push((key.to_string(), default.unwrap_or_default()));
+                continue;
+            }
+        }
+
+        let message = fill_template(prompt.get("message").and_then(|v| v.as_str()).unwrap_or(key), &values);
         let prompt_type = prompt.get("type").and_then(|v| v.as_str()).unwrap_or("text");
 
         let value = match prompt_type {
             "choice" => {
-                let options = prompt.get("options").and_then(|v| v.as_array())?;
-                let items_str = options.iter()
+                let mut options: Vec<String> = prompt.get("options").and_then(|v| v.as_array())?
+                    .iter()
                     .filter_map(|v| v.as_str())
+                    .map(|s| fill_template(s, &values))
+                    .collect();
+                // Show the default option first so it's preselected
+                if let Some(idx) = default.as_ref().and_then(|d| options.iter().position(|o| o == d)) {
+                    let opt = options.remove(idx);
+                    options.insert(0, opt);
+                }
+                let items_str = options.iter()
                     .map(|s| serde_json::json!({"id": s, "name": s}).to_string())
                     .collect::<Vec<_>>()
                     .join("\n");
@@ -538,9 +850,16 @@ fn run_prompts(prompts: &[serde_json::Value], cfg: &Config, frontend_name: Optio
                     .unwrap_or_else(|| sel.trim().to_string())
             }
             _ => {
-                let result = fe.prompt(message);
-                if result.is_empty() { return None; }
-                result
+                let msg = match &default {
+                    Some(d) if !d.is_empty() => format!("{message} [{d}]"),
+                    _ => message,
+                };
+                let result = fe.prompt(&msg);
+                match (result.is_empty(), default) {
+                    (false, _) => result,
+                    (true, Some(d)) => d,
+                    (true, None) => return None,
+                }
             }
         };
         values.push((key.to_string(), value));
@@ -549,8 +868,114 @@ fn run_prompts(prompts: &[serde_json::Value], cfg: &Config, frontend_name: Optio
     Some(values)
 }
 
+/// Substitute `{{key}}` placeholders with previously collected prompt values.
+/// One pass over the text, so placeholders inside substituted values are left alone.
+fn fill_template(text: &str, values: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let key = &rest[start + 2..start + 2 + len];
+        match values.iter().find(|(k, _)| k == key) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + len + 4]),
+        }
+        rest = &rest[start + len + 4..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Evaluate a prompt `when` condition against collected values.
+/// Supports `==`, `!=`, `&&`, `||`, a leading `!`, quoted literals and `{{key}}` references.
+/// A bare operand is true when it is non-empty and not "false" or "0".
+fn eval_condition(cond: &str, values: &[(String, String)]) -> bool {
+    let tokens = condition_tokens(cond);
+    tokens.split(|t| *t == CondToken::Or).any(|any| {
+        any.split(|t| *t == CondToken::And).all(|term| {
+            if let Some(i) = term.iter().position(|t| *t == CondToken::Ne) {
+                condition_operand(&term[..i], values) != condition_operand(&term[i + 1..], values)
+            } else if let Some(i) = term.iter().position(|t| *t == CondToken::Eq) {
+                condition_operand(&term[..i], values) == condition_operand(&term[i + 1..], values)
+            } else if let Some((CondToken::Not, rest)) = term.split_first() {
+                !is_truthy(&condition_operand(rest, values))
+            } else {
+                is_truthy(&condition_operand(term, values))
+            }
+        })
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum CondToken {
+    Quoted(String),
+    Text(String),
+    Eq,
+    Ne,
+    And,
+    Or,
+    Not,
+}
+
+/// Split a condition into operators and operands. Quoted literals are read first,
+/// so operators inside them stay part of the literal.
+fn condition_tokens(cond: &str) -> Vec<CondToken> {
+    let mut tokens = vec![];
+    let mut text = String::new();
+    let mut rest = cond;
+    while let Some(c) = rest.chars().next() {
+        let op = [("&&", CondToken::And), ("||", CondToken::Or), ("==", CondToken::Eq), ("!=", CondToken::Ne), ("!", CondToken::Not)]
+            .into_iter()
+            .find(|(s, _)| rest.starts_with(s));
+        let quoted = (c == '\'' || c == '"').then(|| rest[1..].find(c)).flatten();
+        if let Some((s, op)) = op {
+            push_text(&mut tokens, &mut text);
+            tokens.push(op);
+            rest = &rest[s.len()..];
+        } else if let Some(end) = quoted {
+            push_text(&mut tokens, &mut text);
+            tokens.push(CondToken::Quoted(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    push_text(&mut tokens, &mut text);
+    tokens
+}
+
+fn push_text(tokens: &mut Vec<CondToken>, text: &mut String) {
+    let trimmed = text.trim();
+    if !trimmed.is_empty() {
+        tokens.push(CondToken::Text(trimmed.to_string()));
+    }
+    text.clear();
+}
+
+fn condition_operand(tokens: &[CondToken], values: &[(String, String)]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            CondToken::Quoted(literal) => literal.clone(),
+            CondToken::Text(text) => fill_template(text, values),
+            // An operator where an operand belongs, e.g. a `!` mid-term, is taken literally
+            CondToken::Not => "!".into(),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+fn is_truthy(value: &str) -> bool {
+    !value.is_empty() && value != "false" && value != "0"
+}
+
 /// `pal prompt` command - prompt user via the frontend, print collected values.
-fn prompt_cmd(cfg: &Config, spec: Option<&str>, frontend: Option<&str>) {
+fn prompt_cmd(cfg: &Config, spec: Option<&str>, frontend: Option<&str>, session: &Arc<Session>) {
     let input = match spec {
         Some(s) => s.to_string(),
         None => {
@@ -572,7 +997,7 @@ fn prompt_cmd(cfg: &Config, spec: Option<&str>, frontend: Option<&str>) {
     };
     if prompts.is_empty() { return; }
 
-    if let Some(values) = run_prompts(&prompts, cfg, frontend) {
+    if let Some(values) = run_prompts(&prompts, cfg, frontend, session) {
         if values.len() == 1 {
             print!("{}", values[0].1);
         } else {
@@ -1,73 +1,136 @@
-use crate::action::Action;
-use crate::config::Palette as PaletteConfig;
+use std::cell::OnceCell;
+use std::sync::Arc;
+
+use crate::action;
+use crate::config::{self, ActionChain, Config, Palette as PaletteConfig};
+use crate::context::{Context, Session};
+use crate::dotenv;
+use crate::interpolate;
 use crate::plugin::Plugin;
+use crate::schema;
 use crate::util;
 
 pub struct Palette<'a> {
+    root: &'a Config,
     config: &'a PaletteConfig,
     plugin: Option<Plugin>,
+    session: Arc<Session>,
+    /// Built on first use, so env files are read and `${cmd:...}` runs once per palette
+    context: OnceCell<Context>,
 }
 
 impl<'a> Palette<'a> {
-    pub fn new(config: &'a PaletteConfig) -> Self {
+    pub fn new(root: &'a Config, config: &'a PaletteConfig, session: &Arc<Session>) -> Self {
         let plugin = if config.auto_list && config.auto_pick {
             None
         } else {
             config.base.as_ref().map(|base| Plugin::new(base, config))
         };
-        Self { config, plugin }
+        Self { root, config, plugin, session: session.clone(), context: OnceCell::new() }
     }
 
     pub fn list(&self, query: Option<&str>) -> String {
         let items = if self.config.auto_list {
             self.config.data.as_ref()
                 .and_then(|p| {
+                    let p = &interpolate::resolve(p, &self.session).unwrap_or_else(|e| {
+                        eprintln!("{p}: {e}");
+                        std::process::exit(1);
+                    });
                     let path = util::expand_path(p);
                     let content = std::fs::read_to_string(&path).ok()?;
                     Some(parse_data(&content, p))
                 })
                 .unwrap_or_default()
         } else if let Some(plugin) = &self.plugin {
-            plugin.run("list", query)
+            plugin.run_in("list", query, self.context().clone())
         } else {
             String::new()
         };
-        normalize_items(&items)
+        let source = if self.config.auto_list { &self.config.data } else { &self.config.base };
+        normalize_items(&items, source.as_deref().unwrap_or("palette"), self.session.strict)
     }
 
     pub fn pick(&self, selected: &str) -> String {
-        inject_item_env(selected);
+        let item: serde_json::Value = serde_json::from_str(selected).unwrap_or_default();
+        let ctx = self.context().with_env(&item_env(&item));
+        // Item-level `then` actions run after the default action or plugin pick
+        let then = item.get("then")
+            .and_then(|v| serde_json::from_value::<ActionChain>(v.clone()).ok())
+            .unwrap_or_default();
 
-        if self.config.auto_pick {
-            let action_name = self.config.default_action.as_ref().unwrap();
+        let (chain, value) = if self.config.auto_pick {
+            let default = self.config.default_action.as_ref().unwrap();
             let action_key = self.config.action_key.as_ref().unwrap();
-            let item: serde_json::Value = serde_json::from_str(selected).unwrap_or_default();
             let value = item.get(action_key).and_then(|v| v.as_str()).unwrap_or("");
-            Action::new(action_name).run(value)
+            let chain: Vec<_> = default.0.iter().chain(&then.0).cloned().collect();
+            (chain, value.to_string())
         } else if let Some(plugin) = &self.plugin {
-            plugin.run("pick", Some(selected))
+            let output = plugin.run_in("pick", Some(selected), ctx.clone());
+            if then.0.is_empty() {
+                return output;
+            }
+            (then.0, output.trim_end().to_string())
+        } else {
+            return String::new();
+        };
+
+        // Let actions like cmd know the item needs a terminal
+        let terminal = self.config.terminal
+            || item.get("terminal").and_then(|v| v.as_bool()).unwrap_or(false);
+        let chain: Vec<_> = if terminal {
+            chain.iter().map(|a| a.with_arg("terminal", true.into())).collect()
         } else {
+            chain
+        };
+
+        action::run_chain(&chain, &self.root.action, &value, &ctx).unwrap_or_else(|e| {
+            eprintln!("{e}");
             String::new()
+        })
+    }
+
+    /// Environment and working directory the palette's plugin and actions run with:
+    /// `env_file`, then the `env` table, with `${...}` references resolved
+    fn context(&self) -> &Context {
+        self.context.get_or_init(|| self.build_context())
+    }
+
+    fn build_context(&self) -> Context {
+        let mut env = dotenv::read(&self.config.env_file.0, self.root.general.env_override, &self.session.env);
+        let mut vars: Vec<_> = self.config.env.iter().collect();
+        vars.sort();
+        for (key, value) in vars {
+            let value = interpolate::resolve(value, &self.session).unwrap_or_else(|e| {
+                eprintln!("env {key}: {e}");
+                std::process::exit(1);
+            });
+            env.push((key.clone(), value));
         }
+        let cwd = self.config.cwd.as_deref().map(util::expand_path);
+        Context { env, cwd, ..Context::new(&self.session) }
     }
 }
 
-/// Set PAL_<KEY> env vars from a JSON item so child processes can access them
-fn inject_item_env(selected: &str) {
-    let item: serde_json::Value = serde_json::from_str(selected).unwrap_or_default();
-    if let Some(obj) = item.as_object() {
-        for (k, v) in obj {
+/// PAL_<KEY> variables for the fields of a JSON item, passed to the processes the pick starts.
+/// Kept out of pal's own env so a nested `pal` doesn't see them. Fields that would land on a
+/// variable pal's config reads (`PAL_PROFILE`, `PAL_CONFIG__*`) are skipped.
+fn item_env(item: &serde_json::Value) -> Vec<(String, String)> {
+    let Some(obj) = item.as_object() else { return vec![] };
+    obj.iter()
+        .map(|(k, v)| {
             let val = match v {
                 serde_json::Value::String(s) => s.clone(),
                 other => other.to_string(),
             };
-            std::env::set_var(format!("PAL_{}", k.to_uppercase()), val);
-        }
-    }
+            (format!("PAL_{}", k.to_uppercase()), val)
+        })
+        .filter(|(k, _)| !config::is_reserved_var(k))
+        .collect()
 }
 
 /// Parse data file - supports JSON lines, JSON array, and TOML array-of-tables
-fn parse_data(content: &str, path: &str) -> String {
+pub fn parse_data(content: &str, path: &str) -> String {
     if path.ends_with(".toml") {
         return parse_toml_data(content);
     }
@@ -104,11 +167,28 @@ fn parse_toml_data(content: &str) -> String {
 }
 
 /// Ensure each JSON item has an id field (defaults to name if missing)
-fn normalize_items(items: &str) -> String {
+/// Parse plugin output into items, filling in missing ids.
+/// Malformed lines are dropped; with `--strict` they're logged with the plugin `source`.
+fn normalize_items(items: &str, source: &str, strict: bool) -> String {
     items
         .lines()
-        .filter_map(|line| {
-            let mut item: serde_json::Value = serde_json::from_str(line).ok()?;
+        .enumerate()
+        .filter_map(|(i, line)| {
+            if line.trim().is_empty() {
+                return None;
+            }
+            let parsed = serde_json::from_str::<serde_json::Value>(line);
+            if strict {
+                let errors = match parsed {
+                    Ok(ref item) => schema::validate_item(item).0,
+                    Err(ref e) => vec![format!("invalid JSON: {e}")],
+                };
+                if !errors.is_empty() {
+                    eprintln!("pal: {source}: line {}: {}: {line}", i + 1, errors.join("; "));
+                    return None;
+                }
+            }
+            let mut item = parsed.ok()?;
             if item.get("id").is_none() {
                 let name = item.get("name").and_then(|v| v.as_str()).map(String::from);
                 if let (Some(name), Some(obj)) = (name, item.as_object_mut()) {
@@ -3,7 +3,8 @@ use std::process;
 
 use serde::Serialize;
 
-use crate::{builtin, util};
+use crate::context::Context;
+use crate::{builtin, interpolate, util};
 
 pub struct Plugin {
     base: String,
@@ -28,22 +29,55 @@ impl Plugin {
                     eprintln!("plugin.toml missing 'command'");
                     process::exit(1);
                 });
+            // Remote code only runs once its repo is trusted
+            if let Err(e) = crate::remote::verify_trust(base) {
+                eprintln!("{e}");
+                process::exit(1);
+            }
             Some(expanded.join(cmd))
         };
 
         Self { base: base.to_string(), exec, config }
     }
 
-    pub fn run(&self, cmd: &str, input: Option<&str>) -> String {
-        let config_str = serde_json::to_string(&self.config).unwrap();
-        std::env::set_var("_PAL_PLUGIN_CONFIG", &config_str);
+    /// Run a plugin command with the environment and working directory of `ctx`
+    pub fn run_in(&self, cmd: &str, input: Option<&str>, ctx: Context) -> String {
+        let ctx = self.context(ctx);
 
         if let Some(exec) = &self.exec {
-            util::run_command(exec, &[cmd], input)
+            util::run_command(exec, &[cmd], input, &ctx)
         } else {
-            builtin::run(&self.base, cmd, input)
+            builtin::run(&self.base, cmd, input, &ctx)
         }
     }
+
+    /// plugin.toml fields merged with the user config
+    pub fn config(&self) -> &toml::Value {
+        &self.config
+    }
+
+    /// Like `run_in`, but reports a failing plugin as an error instead of ignoring it
+    pub fn try_run(&self, cmd: &str, input: Option<&str>, ctx: Context) -> Result<String, String> {
+        let ctx = self.context(ctx);
+
+        if let Some(exec) = &self.exec {
+            util::run_command_checked(exec, &[cmd], input, &ctx)
+        } else if let Some(name) = self.base.strip_prefix("builtin/actions/") {
+            builtin::actions::run_action(name, input.unwrap_or(""), &ctx)
+        } else {
+            Ok(builtin::run(&self.base, cmd, input, &ctx))
+        }
+    }
+
+    /// Add this plugin's config to a context, resolving `${...}` references now that it's about to run
+    fn context(&self, ctx: Context) -> Context {
+        let config = serde_json::to_value(&self.config).unwrap();
+        let config = interpolate::resolve_json(&config, &ctx.session).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", self.base);
+            process::exit(1);
+        });
+        Context { config, ..ctx }
+    }
 }
 
 fn load_plugin_toml(base: &str, expanded: &Path) -> toml::Value {
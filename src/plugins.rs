@@ -0,0 +1,238 @@
+use std::path::Path;
+use std::process;
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::action;
+use crate::builtin::desktop;
+use crate::config::{self, ActionChain, Config};
+use crate::palette;
+use crate::remote;
+use crate::util;
+
+/// Install a remote plugin and append a `[palette.<name>]` section to the user config
+pub fn add(source: &str, name: Option<&str>) {
+    if !remote::is_remote(source) {
+        eprintln!("not a remote source: {source}");
+        process::exit(1);
+    }
+    let Some(plugin) = config::load_plugin_toml(source, true) else {
+        eprintln!("no plugin.toml found at {source}");
+        process::exit(1);
+    };
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => plugin
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| source_name(source)),
+    };
+
+    if let Err(e) = register("palette", &name, source) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
+
+/// Append a `[<table>.<name>]` section with the given base to the user config, keeping its formatting
+pub fn register(table: &str, name: &str, base: &str) -> Result<(), String> {
+    let path = config::user_config_path();
+    let mut doc = load_doc(&path);
+    let sections = doc.entry(table).or_insert_with(|| {
+        let mut t = Table::new();
+        t.set_implicit(true);
+        Item::Table(t)
+    });
+    let Some(sections) = sections.as_table_mut() else {
+        return Err(format!("{}: {table} is not a table", path.display()));
+    };
+    if sections.contains_key(name) {
+        return Err(format!("{table} '{name}' already exists in {}, pick another name", path.display()));
+    }
+
+    let mut section = Table::new();
+    section.insert("base", toml_edit::value(base));
+    sections.insert(name, Item::Table(section));
+    save_doc(&path, &doc);
+    println!("added {table} '{name}' to {}", path.display());
+    Ok(())
+}
+
+/// Remove a palette from the user config, then prune its clone if nothing else uses it
+pub fn remove(cfg: &mut Config, name: &str, yes: bool) {
+    let path = config::user_config_path();
+    let mut doc = load_doc(&path);
+    let removed = doc
+        .get_mut("palette")
+        .and_then(|p| p.as_table_like_mut())
+        .and_then(|p| p.remove(name));
+    if removed.is_none() {
+        eprintln!("palette '{name}' not found in {}", path.display());
+        process::exit(1);
+    }
+    save_doc(&path, &doc);
+    println!("removed palette '{name}' from {}", path.display());
+
+    let base = cfg.palette.remove(name).and_then(|p| p.base);
+    if let Some(base) = base.filter(|b| remote::is_remote(b)) {
+        remote::prune(&used_sources(cfg), Some(&base), yes);
+    }
+}
+
+/// Print where a palette's plugin comes from, its plugin.toml fields and requirements status
+pub fn info(cfg: &Config, name: &str) {
+    let Some(palette) = cfg.palette.get(name) else {
+        eprintln!("palette not found: {name}");
+        process::exit(1);
+    };
+    let base = palette.base.as_deref().unwrap_or("-");
+
+    println!("{:<10}{name}", "palette");
+    println!("{:<10}{base}", "base");
+    if let Some(ref data) = palette.data {
+        println!("{:<10}{data}", "data");
+    }
+    for (key, value) in remote::describe(base).unwrap_or_default() {
+        println!("{key:<10}{value}");
+    }
+
+    let Some(toml::Value::Table(plugin)) = config::load_plugin_toml(base, false) else {
+        if base != "-" {
+            println!("\nplugin.toml not available");
+        }
+        return;
+    };
+
+    println!("\nplugin.toml:");
+    for (key, value) in plugin.iter().filter(|(k, _)| *k != "requirements") {
+        println!("  {key} = {value}");
+    }
+
+    let requirements: Vec<&str> = plugin
+        .get("requirements")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    if !requirements.is_empty() {
+        println!("\nrequirements:");
+        for req in requirements {
+            let status = if desktop::has_command(req) { "ok" } else { "missing" };
+            println!("  {req:<16}{status}");
+        }
+    }
+}
+
+/// Delete clones and sparse paths the loaded config no longer references
+pub fn prune(cfg: &Config, yes: bool) {
+    remote::prune(&used_sources(cfg), None, yes);
+}
+
+/// Remote sources the config refers to: palette bases and data, frontends and actions, also
+/// in profiles that aren't active. Actions named in `default_action` or in the `then` of
+/// items in data files count, fallback actions included.
+fn used_sources(cfg: &Config) -> Vec<String> {
+    let palette_actions = cfg
+        .palette
+        .values()
+        .flat_map(|p| p.default_action.iter().flat_map(|chain| chain.0.iter()))
+        .map(|action| action.name().to_string())
+        .chain(then_actions(cfg));
+    let actions = cfg
+        .action
+        .keys()
+        .cloned()
+        .chain(palette_actions)
+        .filter(|name| cfg.action.get(name).and_then(|a| a.template.as_ref()).is_none())
+        .filter_map(|name| action::plugin_base(&name, &cfg.action).ok());
+
+    let mut sources: Vec<String> = cfg
+        .palette
+        .values()
+        .flat_map(|p| [p.base.clone(), p.data.clone()])
+        .flatten()
+        .chain(cfg.frontend.values().filter_map(|f| f.base.clone()))
+        .chain(actions)
+        .filter(|s| remote::is_remote(s))
+        .collect();
+    for profile in cfg.profile.values() {
+        for value in profile.overlay.values() {
+            profile_sources(cfg, value, &mut sources);
+        }
+    }
+    sources.sort();
+    sources.dedup();
+    sources
+}
+
+/// Action names in the `then` of items in palette data files that are local or already cloned
+fn then_actions(cfg: &Config) -> Vec<String> {
+    let mut names = vec![];
+    for data in cfg.palette.values().filter_map(|p| p.data.as_deref()) {
+        let path = match remote::is_remote(data) {
+            true => remote::local_path(data),
+            false => Some(util::expand_path(data)),
+        };
+        let Some(content) = path.and_then(|p| std::fs::read_to_string(p).ok()) else { continue };
+        for line in palette::parse_data(&content, data).lines() {
+            let then = serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .and_then(|item| item.get("then").cloned())
+                .and_then(|v| serde_json::from_value::<ActionChain>(v).ok());
+            names.extend(then.iter().flat_map(|chain| chain.0.iter()).map(|a| a.name().to_string()));
+        }
+    }
+    names
+}
+
+/// Remote sources in a profile overlay: any remote string, and the plugins of action names
+/// in `default_action`
+fn profile_sources(cfg: &Config, value: &serde_json::Value, sources: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) if remote::is_remote(s) => sources.push(s.clone()),
+        serde_json::Value::Object(map) => {
+            if let Some(Ok(chain)) = map.get("default_action").map(|v| serde_json::from_value::<ActionChain>(v.clone())) {
+                sources.extend(
+                    chain.0.iter()
+                        .filter(|a| cfg.action.get(a.name()).and_then(|a| a.template.as_ref()).is_none())
+                        .filter_map(|a| action::plugin_base(a.name(), &cfg.action).ok())
+                        .filter(|s| remote::is_remote(s)),
+                );
+            }
+            for value in map.values() {
+                profile_sources(cfg, value, sources);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for value in items {
+                profile_sources(cfg, value, sources);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Default palette name for a source: the last path segment without its ref
+fn source_name(source: &str) -> String {
+    let last = source.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or(source);
+    last.split('@').next().unwrap_or(last).to_string()
+}
+
+pub fn load_doc(path: &Path) -> DocumentMut {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    content.parse().unwrap_or_else(|e| {
+        eprintln!("{}: {e}", path.display());
+        process::exit(1);
+    })
+}
+
+pub fn save_doc(path: &Path, doc: &DocumentMut) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).ok();
+    }
+    if let Err(e) = std::fs::write(path, doc.to_string()) {
+        eprintln!("failed to write {}: {e}", path.display());
+        process::exit(1);
+    }
+}
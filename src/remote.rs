@@ -1,45 +1,111 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-/// Parsed github:user/repo/path[@ref] URL
-struct GithubUrl {
-    user: String,
+use serde::Serialize;
+
+use crate::lock::Lockfile;
+use crate::trust::{self, TrustStore};
+
+/// Parsed remote plugin source:
+/// - `github:user/repo/path[@ref]`
+/// - `gitlab:group/repo/path[@ref]` (or `gitlab:group/sub/repo//path[@ref]` for subgroups)
+/// - `git+https://host/repo.git//path[@ref]` (any git URL scheme)
+/// - `git+file:///path/to/repo//path[@ref]`
+struct RemoteUrl {
+    /// Host used in the clone directory layout ("file" for local repos)
+    host: String,
+    /// Repository path on the host, e.g. "user/repo"
     repo: String,
     path: String,
     git_ref: String,
+    clone_url: String,
 }
 
-impl GithubUrl {
-    /// Parse "github:user/repo/path/to/plugin" or "github:user/repo/path@ref"
+impl RemoteUrl {
     fn parse(base: &str) -> Option<Self> {
-        let rest = base.strip_prefix("github:")?;
+        if let Some(rest) = base.strip_prefix("github:") {
+            Self::parse_hosted("github.com", rest)
+        } else if let Some(rest) = base.strip_prefix("gitlab:") {
+            Self::parse_hosted("gitlab.com", rest)
+        } else if let Some(rest) = base.strip_prefix("git+") {
+            Self::parse_git(rest)
+        } else {
+            None
+        }
+    }
+
+    /// Parse "user/repo/path@ref" for a known forge host
+    fn parse_hosted(host: &str, rest: &str) -> Option<Self> {
+        let (path_part, git_ref) = split_ref(rest, rest.rfind('@'));
+
+        // An explicit `//` separates the repo from the plugin path (needed for subgroups)
+        let (repo, path) = match path_part.split_once("//") {
+            Some((repo, path)) => (repo.to_string(), path.to_string()),
+            None => {
+                let parts: Vec<&str> = path_part.splitn(3, '/').collect();
+                if parts.len() < 2 {
+                    return None;
+                }
+                (format!("{}/{}", parts[0], parts[1]), parts.get(2).unwrap_or(&"").to_string())
+            }
+        };
+        if repo.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            host: host.to_string(),
+            clone_url: format!("https://{host}/{repo}.git"),
+            repo,
+            path,
+            git_ref,
+        })
+    }
+
+    /// Parse "scheme://host/repo.git//path@ref"
+    fn parse_git(rest: &str) -> Option<Self> {
+        let (scheme, after_scheme) = rest.split_once("://")?;
 
-        // Split off @ref if present
-        let (path_part, git_ref) = if let Some(idx) = rest.rfind('@') {
-            (&rest[..idx], rest[idx + 1..].to_string())
+        // "host/repo.git//path@ref": the ref follows the plugin path, or the repo when there's no path
+        let (url_part, path_part) = after_scheme.split_once("//").unwrap_or((after_scheme, ""));
+        let (url_part, path, git_ref) = if path_part.is_empty() {
+            let at = url_part.rfind('@').filter(|&i| !url_part[i..].contains('/'));
+            let (url, git_ref) = split_ref(url_part, at);
+            (url, String::new(), git_ref)
         } else {
-            (rest, "main".to_string())
+            let (path, git_ref) = split_ref(path_part, path_part.rfind('@'));
+            (url_part.to_string(), path, git_ref)
         };
 
-        let parts: Vec<&str> = path_part.splitn(3, '/').collect();
-        if parts.len() < 3 {
+        let (host, repo) = if scheme == "file" {
+            ("file".to_string(), url_part.trim_start_matches('/').to_string())
+        } else {
+            let (authority, repo) = url_part.split_once('/')?;
+            // Drop user info ("git@") and port from the directory layout
+            let host = authority.rsplit('@').next().unwrap_or(authority);
+            let host = host.split(':').next().unwrap_or(host);
+            (host.to_string(), repo.to_string())
+        };
+        let repo = repo.trim_end_matches('/').trim_end_matches(".git").to_string();
+        if repo.is_empty() {
             return None;
         }
 
         Some(Self {
-            user: parts[0].to_string(),
-            repo: parts[1].to_string(),
-            path: parts[2].to_string(),
+            host,
+            repo,
+            path,
             git_ref,
+            clone_url: format!("{scheme}://{url_part}"),
         })
     }
 
     /// Local directory where repo is cloned
     fn repo_dir(&self) -> PathBuf {
-        dirs::data_dir()
-            .unwrap_or_else(|| PathBuf::from("~/.local/share"))
-            .join("pal/plugins/github.com")
-            .join(&self.user)
+        plugins_base()
+            .join(&self.host)
             .join(&self.repo)
             .join(&self.git_ref)
     }
@@ -49,24 +115,79 @@ impl GithubUrl {
         self.repo_dir().join(&self.path)
     }
 
-    /// GitHub clone URL
-    fn clone_url(&self) -> String {
-        format!("https://github.com/{}/{}.git", self.user, self.repo)
+    /// Key identifying the clone in pal.lock, e.g. "github.com/zcag/pal/main"
+    fn key(&self) -> String {
+        format!("{}/{}", self.repo_id(), self.git_ref)
+    }
+
+    /// Repo identity used by the trust store, e.g. "github.com/zcag/pal"
+    fn repo_id(&self) -> String {
+        format!("{}/{}", self.host, self.repo)
+    }
+}
+
+/// Split "text@ref" at the given `@` index, defaulting the ref to "main"
+fn split_ref(s: &str, at: Option<usize>) -> (String, String) {
+    match at {
+        Some(idx) => (s[..idx].to_string(), s[idx + 1..].to_string()),
+        None => (s.to_string(), "main".to_string()),
     }
 }
 
-/// Check if base is a github: URL and ensure it's cloned locally.
-/// Returns the local path to the plugin directory.
-pub fn ensure_github(base: &str) -> Option<PathBuf> {
-    let url = GithubUrl::parse(base)?;
+/// Check if a base or data path refers to a remote plugin source
+pub fn is_remote(base: &str) -> bool {
+    base.starts_with("github:") || base.starts_with("gitlab:") || base.starts_with("git+")
+}
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Never touch git for remote plugins; missing plugins are reported as errors.
+/// Nested pal invocations stay offline through the session's `_PAL_OFFLINE`.
+pub fn set_offline() {
+    OFFLINE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Local path of an already cloned remote source, without touching git
+pub fn local_path(base: &str) -> Option<PathBuf> {
+    let plugin_dir = RemoteUrl::parse(base)?.plugin_dir();
+    plugin_dir.exists().then_some(plugin_dir)
+}
+
+/// Ensure a remote source is cloned locally.
+/// Returns the local path to the plugin directory, or None if base isn't a remote source
+/// or it's missing in offline mode.
+pub fn ensure(base: &str) -> Option<PathBuf> {
+    let url = RemoteUrl::parse(base)?;
 
     let repo_dir = url.repo_dir();
     let plugin_dir = url.plugin_dir();
 
-    // Clone if repo doesn't exist
+    if plugin_dir.exists() {
+        // Clones made before pal.lock existed (or after it was deleted) still get pinned
+        if !is_offline() {
+            record_in_lock(&url, base, &mut Lockfile::load());
+        }
+        return Some(plugin_dir);
+    }
+    if is_offline() {
+        eprintln!("pal: offline, remote plugin not installed: {base}");
+        return None;
+    }
+
+    let mut lock = Lockfile::load();
+
+    // Clone if repo doesn't exist, honoring the commit pinned in pal.lock
     if !repo_dir.join(".git").exists() {
         clone_repo(&url, &repo_dir);
+        if let Some(commit) = lock.commit(&url.key()) {
+            checkout_commit(&repo_dir, commit);
+        }
     }
+    record_in_lock(&url, base, &mut lock);
 
     // Add path to sparse checkout if not already present
     if !plugin_dir.exists() {
@@ -76,79 +197,617 @@ pub fn ensure_github(base: &str) -> Option<PathBuf> {
     Some(plugin_dir)
 }
 
+/// Pin a clone that isn't in pal.lock yet at its checked-out commit and record the source using it
+fn record_in_lock(url: &RemoteUrl, base: &str, lock: &mut Lockfile) {
+    let key = url.key();
+    let mut changed = false;
+    if lock.commit(&key).is_none() {
+        let commit = git_rev(&url.repo_dir(), "HEAD");
+        if !commit.is_empty() {
+            lock.set_commit(&key, &url.clone_url, &url.git_ref, &commit);
+            changed = true;
+        }
+    }
+    changed |= lock.add_source(&key, base);
+    if changed {
+        lock.save();
+    }
+}
+
+/// Check out a pinned commit in a shallow clone, fetching it if needed
+fn checkout_commit(repo_dir: &Path, commit: &str) {
+    let repo = repo_dir.to_string_lossy();
+    if git_rev(repo_dir, "HEAD") == commit {
+        return;
+    }
+    let ok = git_quiet(&["-C", &repo, "fetch", "--quiet", "--depth=1", "origin", commit])
+        && git_quiet(&["-C", &repo, "checkout", "--quiet", "--detach", commit]);
+    if !ok {
+        eprintln!("pal: could not check out locked commit {commit} in {}", repo_dir.display());
+    }
+}
+
+fn git_quiet(args: &[&str]) -> bool {
+    Command::new("git")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Check that a remote plugin's code is trusted before running it.
+/// A repo or ref seen for the first time, and any update touching the checked-out paths, need confirmation.
+pub fn verify_trust(base: &str) -> Result<(), String> {
+    let Some(url) = RemoteUrl::parse(base) else { return Ok(()) };
+    let repo_dir = url.repo_dir();
+    let repo_id = url.repo_id();
+    let head = git_rev(&repo_dir, "HEAD");
+    let paths = sparse_paths(&repo_dir, &url.path);
+    let mut store = TrustStore::load();
+
+    let reviewed = store.repos.get(&repo_id).map(|r| r.refs.get(&url.git_ref).cloned());
+    let prompt = match reviewed {
+        Some(Some(commit)) if commit == head => return Ok(()),
+        Some(Some(commit)) => match changed_files(&repo_dir, &commit, &head, &paths) {
+            Some(files) if files.is_empty() => None,
+            Some(files) => Some((
+                format!("{repo_id} changed since last review. Run the new version?"),
+                diff_lines(&repo_dir, &commit, &head, &files),
+            )),
+            None => Some((
+                format!("{repo_id} changed since last review. Run the new version?"),
+                files(&repo_dir, &head, &paths),
+            )),
+        },
+        Some(None) => Some((
+            format!("Trust {repo_id}@{} and run its plugin code?", url.git_ref),
+            files(&repo_dir, &head, &paths),
+        )),
+        None => Some((
+            format!("Trust {repo_id} and run its plugin code?"),
+            files(&repo_dir, &head, &paths),
+        )),
+    };
+
+    if let Some((message, details)) = prompt {
+        if !trust::confirm(&message, &details) {
+            return Err(format!("{repo_id} is not trusted, review it and run `pal trust {base}`"));
+        }
+    }
+    store.repos.entry(repo_id).or_default().refs.insert(url.git_ref, head);
+    store.save();
+    Ok(())
+}
+
+/// Paths checked out in a sparse clone, falling back to the plugin's own path
+fn sparse_paths(repo: &Path, path: &str) -> Vec<String> {
+    let paths: Vec<String> = git_lines(repo, &["sparse-checkout", "list"])
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect();
+    if !paths.is_empty() {
+        paths
+    } else if path.is_empty() {
+        vec![]
+    } else {
+        vec![path.to_string()]
+    }
+}
+
+/// Files under the given paths at a commit, the whole repo without paths.
+/// Executables are marked as `runs:`, anything a plugin could source or read as `file:`.
+fn files(repo: &Path, commit: &str, paths: &[String]) -> Vec<String> {
+    let mut args = vec!["ls-tree", "-r", commit, "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_lines(repo, &args)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|line| {
+            let (meta, file) = line.split_once('\t')?;
+            let label = if meta.starts_with("100755") { "runs" } else { "file" };
+            Some(format!("{label}: {file}"))
+        })
+        .collect()
+}
+
+/// Files under the given paths changed between two commits, or None if the diff isn't available
+fn changed_files(repo: &Path, old: &str, new: &str, paths: &[String]) -> Option<Vec<String>> {
+    let mut args = vec!["diff", "--name-only", old, new, "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_lines(repo, &args)
+}
+
+fn diff_lines(repo: &Path, old: &str, new: &str, files: &[String]) -> Vec<String> {
+    const MAX_LINES: usize = 200;
+    let mut args = vec!["diff", old, new, "--"];
+    args.extend(files.iter().map(String::as_str));
+    let mut lines = git_lines(repo, &args).unwrap_or_else(|| files.to_vec());
+    if lines.len() > MAX_LINES {
+        let more = lines.len() - MAX_LINES;
+        lines.truncate(MAX_LINES);
+        lines.push(format!("... ({more} more lines)"));
+    }
+    lines
+}
+
+/// Trust a remote repo at the current commit of each cloned ref.
+/// Accepts any source form (`github:user/repo[/path]`, `git+...`) or a repo id.
+pub fn trust(source: &str) {
+    let repo_id = source_repo_id(source);
+    let mut store = TrustStore::load();
+    let entry = store.repos.entry(repo_id.clone()).or_default();
+    let mut refs = vec![];
+    collect_repos(&plugins_base().join(&repo_id), &mut refs);
+    for repo in refs {
+        let git_ref = repo.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        entry.refs.insert(git_ref, git_rev(&repo, "HEAD"));
+    }
+    store.save();
+    println!("trusted {repo_id}");
+}
+
+pub fn untrust(source: &str) {
+    let repo_id = source_repo_id(source);
+    let mut store = TrustStore::load();
+    if store.repos.remove(&repo_id).is_some() {
+        store.save();
+        println!("untrusted {repo_id}");
+    } else {
+        println!("{repo_id} is not trusted");
+    }
+}
+
+/// List trusted repos with their reviewed commits
+pub fn list_trusted() {
+    let store = TrustStore::load();
+    if store.repos.is_empty() {
+        println!("no trusted remote repos");
+        return;
+    }
+    for (repo_id, repo) in &store.repos {
+        let refs = repo.refs.iter()
+            .map(|(r, commit)| format!("{r}@{}", &commit[..commit.len().min(7)]))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("{repo_id} {refs}");
+    }
+}
+
+fn source_repo_id(source: &str) -> String {
+    RemoteUrl::parse(source)
+        .map(|url| url.repo_id())
+        .unwrap_or_else(|| source.trim_end_matches('/').to_string())
+}
+
 /// Return the base directory where remote plugins are stored
 fn plugins_base() -> PathBuf {
     dirs::data_dir()
         .unwrap_or_else(|| PathBuf::from("~/.local/share"))
-        .join("pal/plugins/github.com")
+        .join("pal/plugins")
 }
 
 /// Find all cloned repo directories (dirs containing .git)
 fn find_repos() -> Vec<PathBuf> {
+    // Structure: base/{host}/{repo path...}/{ref}/.git
+    let mut repos = vec![];
+    collect_repos(&plugins_base(), &mut repos);
+    repos.sort();
+    repos
+}
+
+fn collect_repos(dir: &Path, repos: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.join(".git").exists() {
+            repos.push(path);
+        } else {
+            collect_repos(&path, repos);
+        }
+    }
+}
+
+/// Source, pin and trust details of a remote plugin, for `pal plugins info`
+pub fn describe(base: &str) -> Option<Vec<(&'static str, String)>> {
+    let url = RemoteUrl::parse(base)?;
+    let repo_dir = url.repo_dir();
+    let lock = Lockfile::load();
+    let trusted = TrustStore::load()
+        .repos
+        .get(&url.repo_id())
+        .and_then(|r| r.refs.get(&url.git_ref).cloned());
+
+    let mut info = vec![
+        ("repo", format!("{} ({})", url.repo_id(), url.clone_url)),
+        ("ref", url.git_ref.clone()),
+        ("pinned", lock.commit(&url.key()).unwrap_or("-").to_string()),
+    ];
+    if url.plugin_dir().exists() {
+        info.push(("commit", git_short_log(&repo_dir)));
+        info.push(("path", url.plugin_dir().display().to_string()));
+    } else {
+        info.push(("commit", "not installed".to_string()));
+    }
+    info.push(("trusted", trusted.unwrap_or_else(|| "no".to_string())));
+    Some(info)
+}
+
+/// Delete clones no source in `sources` uses, and drop their unused sparse paths.
+/// Only clones pinned in pal.lock are touched: a clone the lock doesn't know may belong to
+/// another config. Asks before changing anything unless `yes`.
+pub fn prune(sources: &[String], only: Option<&str>, yes: bool) {
+    let urls: Vec<RemoteUrl> = sources.iter().filter_map(|s| RemoteUrl::parse(s)).collect();
+    let only = only.and_then(RemoteUrl::parse).map(|url| url.key());
+    let mut lock = Lockfile::load();
     let base = plugins_base();
-    if !base.exists() {
-        return vec![];
+
+    // (key, clone, sparse paths to keep, or None to delete the clone)
+    let mut plan: Vec<(String, PathBuf, Option<Vec<String>>)> = vec![];
+    let mut details = vec![];
+    for repo in find_repos() {
+        let key = repo.strip_prefix(&base).unwrap_or(&repo).to_string_lossy().into_owned();
+        if only.as_ref().is_some_and(|o| *o != key) {
+            continue;
+        }
+        let Some(entry) = lock.repos.get(&key) else {
+            if only.is_none() {
+                println!("{key}: not in {}, skipped", Lockfile::path().display());
+            }
+            continue;
+        };
+        let used: Vec<&str> = urls.iter().filter(|u| u.key() == key).map(|u| u.path.as_str()).collect();
+
+        if used.is_empty() {
+            details.push(format!("delete {key} (used by {})", entry.sources.iter().cloned().collect::<Vec<_>>().join(", ")));
+            plan.push((key, repo, None));
+            continue;
+        }
+
+        // Drop sparse paths that no source lives in or under
+        let Some(current) = git_lines(&repo, &["sparse-checkout", "list"]) else { continue };
+        let (keep, unused): (Vec<String>, Vec<String>) = current.into_iter().partition(|path| {
+            used.iter().any(|u| u == path || u.starts_with(&format!("{path}/")) || path.starts_with(&format!("{u}/")))
+        });
+        if unused.is_empty() || keep.is_empty() {
+            continue;
+        }
+        details.push(format!("{key}: drop {}", unused.join(", ")));
+        plan.push((key, repo, Some(keep)));
     }
-    let mut repos = vec![];
-    // Structure: base/{user}/{repo}/{ref}/.git
-    if let Ok(users) = std::fs::read_dir(&base) {
-        for user in users.flatten() {
-            if let Ok(user_repos) = std::fs::read_dir(user.path()) {
-                for repo in user_repos.flatten() {
-                    if let Ok(refs) = std::fs::read_dir(repo.path()) {
-                        for r in refs.flatten() {
-                            if r.path().join(".git").exists() {
-                                repos.push(r.path());
-                            }
-                        }
+
+    if plan.is_empty() {
+        if only.is_none() {
+            println!("nothing to prune");
+        }
+        return;
+    }
+    if !yes && !trust::confirm("Prune these remote plugin clones?", &details) {
+        println!("nothing pruned (pass --yes to prune without asking)");
+        return;
+    }
+
+    for (key, repo, keep) in plan {
+        match keep {
+            None => {
+                if let Err(e) = std::fs::remove_dir_all(&repo) {
+                    eprintln!("failed to remove {}: {e}", repo.display());
+                    continue;
+                }
+                remove_empty_parents(&repo, &base);
+                lock.repos.remove(&key);
+                println!("removed {key}");
+            }
+            Some(keep) => {
+                let mut args = vec!["-C".to_string(), repo.to_string_lossy().into_owned(), "sparse-checkout".into(), "set".into()];
+                args.extend(keep);
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                if git_quiet(&args) {
+                    if let Some(entry) = lock.repos.get_mut(&key) {
+                        entry.sources.retain(|s| sources.contains(s));
                     }
+                    println!("pruned {key}");
                 }
             }
         }
     }
-    repos
+    lock.save();
+}
+
+/// Remove directories left empty between a deleted clone and the plugins base
+fn remove_empty_parents(dir: &Path, base: &Path) {
+    let mut dir = dir.parent();
+    while let Some(d) = dir.filter(|d| d.starts_with(base) && *d != base) {
+        if std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+/// Most git processes status checks and updates run at once
+const MAX_JOBS: usize = 8;
+
+/// Outcome of checking or updating one clone
+#[derive(Serialize)]
+struct RepoStatus {
+    repo: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    old: String,
+    new: String,
+    status: Status,
+    /// Sparse plugin paths with changes between old and new
+    changed: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
-/// List installed remote plugins with up-to-date status
-pub fn list_plugins() {
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    UpToDate,
+    Available,
+    Updated,
+    /// Ref is a tag or commit, nothing to pull
+    Pinned,
+    Offline,
+    Failed,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Self::UpToDate => "up to date",
+            Self::Available => "update available",
+            Self::Updated => "updated",
+            Self::Pinned => "pinned",
+            Self::Offline => "offline",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// List installed remote plugins, checking for updates concurrently
+pub fn list_plugins(json: bool) {
     let repos = find_repos();
-    if repos.is_empty() {
+    if repos.is_empty() && !json {
         println!("no remote plugins installed");
         return;
     }
+    let offline = is_offline();
+    let statuses = parallel_map(&repos, |repo| check_repo(repo, offline, false));
+    report(&statuses, json);
+}
+
+/// Update remote plugins and refresh their pins in pal.lock.
+/// `filter` limits the update to one plugin: a repo key, a source, or a plugin name.
+/// With `dry_run` only fetches and reports what would change.
+pub fn update_plugins(filter: Option<&str>, dry_run: bool, json: bool) {
+    if is_offline() {
+        eprintln!("pal: offline, not updating remote plugins");
+        return;
+    }
+    let mut lock = Lockfile::load();
     let base = plugins_base();
-    for repo in &repos {
-        let rel = repo.strip_prefix(&base).unwrap_or(repo);
-        let commit = git_short_log(repo);
-        let status = check_status(repo);
-        println!("{} {} {}", rel.display(), commit, status);
+    let repos: Vec<_> = find_repos()
+        .into_iter()
+        .filter(|repo| {
+            let key = repo.strip_prefix(&base).unwrap_or(repo).to_string_lossy().into_owned();
+            match filter {
+                Some(f) => matches_plugin(&lock, &key, f),
+                None => true,
+            }
+        })
+        .collect();
+    if repos.is_empty() && !json {
+        match filter {
+            Some(f) => println!("no remote plugin matching '{f}'"),
+            None => println!("no remote plugins to update"),
+        }
+        return;
+    }
+
+    let statuses = parallel_map(&repos, |repo| check_repo(repo, false, !dry_run));
+    for (repo, status) in repos.iter().zip(&statuses) {
+        if status.status == Status::Updated {
+            let url = git_output(repo, &["remote", "get-url", "origin"]);
+            lock.set_commit(&status.repo, &url, &status.git_ref, &status.new);
+        }
+    }
+    if !dry_run {
+        lock.save();
+    }
+    report(&statuses, json);
+}
+
+/// Fetch a clone's ref and compare it to the checked out commit, fast-forwarding when `apply` is set
+fn check_repo(repo: &Path, offline: bool, apply: bool) -> RepoStatus {
+    let key = repo.strip_prefix(plugins_base()).unwrap_or(repo).to_string_lossy().into_owned();
+    let git_ref = repo.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let old = git_rev(repo, "HEAD");
+    let mut status = RepoStatus {
+        repo: key,
+        git_ref,
+        new: old.clone(),
+        old,
+        status: Status::UpToDate,
+        changed: vec![],
+        error: None,
+    };
+
+    if offline {
+        status.status = Status::Offline;
+        return status;
+    }
+    // Clones of a branch track it in origin/<ref>; tags and commits have nothing to follow
+    let tracking = format!("refs/remotes/origin/{}", status.git_ref);
+    if git_output(repo, &["rev-parse", "--verify", "--quiet", &tracking]).is_empty() {
+        status.status = Status::Pinned;
+        return status;
+    }
+
+    let repo_str = repo.to_string_lossy();
+    if let Err(e) = git_checked(&["-C", &repo_str, "fetch", "--quiet", "origin", &status.git_ref]) {
+        status.status = Status::Failed;
+        status.error = Some(e);
+        return status;
+    }
+    status.new = git_rev(repo, &tracking);
+    if status.new == status.old {
+        return status;
+    }
+    status.changed = changed_paths(repo, &status.old, &status.new);
+
+    if !apply {
+        status.status = Status::Available;
+        return status;
+    }
+    // A clone pinned by pal.lock sits on a detached HEAD; go back to its branch first
+    if git_output(repo, &["rev-parse", "--abbrev-ref", "HEAD"]) == "HEAD" {
+        git_quiet(&["-C", &repo_str, "checkout", "--quiet", &status.git_ref]);
+    }
+    match git_checked(&["-C", &repo_str, "merge", "--ff-only", "--quiet", &tracking]) {
+        Ok(()) => status.status = Status::Updated,
+        Err(e) => {
+            status.status = Status::Failed;
+            status.error = Some(e);
+        }
+    }
+    status
+}
+
+/// Sparse checkout paths with changed files between two commits
+fn changed_paths(repo: &Path, old: &str, new: &str) -> Vec<String> {
+    let files = git_lines(repo, &["diff", "--name-only", old, new]).unwrap_or_default();
+    let paths = git_lines(repo, &["sparse-checkout", "list"]).unwrap_or_default();
+    paths
+        .into_iter()
+        .filter(|path| files.iter().any(|f| f == path || f.starts_with(&format!("{path}/"))))
+        .collect()
+}
+
+/// Print statuses as a table or JSON
+fn report(statuses: &[RepoStatus], json: bool) {
+    if json {
+        match serde_json::to_string_pretty(statuses) {
+            Ok(out) => println!("{out}"),
+            Err(e) => eprintln!("failed to serialize status: {e}"),
+        }
+        return;
+    }
+
+    let short = |commit: &str| commit.chars().take(7).collect::<String>();
+    let mut rows = vec![["REPO", "REF", "COMMIT", "STATUS", "CHANGED"].map(String::from)];
+    for s in statuses {
+        let commit = if s.old == s.new { short(&s.old) } else { format!("{}→{}", short(&s.old), short(&s.new)) };
+        let status = match s.error {
+            Some(ref e) => format!("{}: {e}", s.status.label()),
+            None => s.status.label().to_string(),
+        };
+        rows.push([s.repo.clone(), s.git_ref.clone(), commit, status, s.changed.join(", ")]);
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line: Vec<String> = row.iter().zip(widths).map(|(cell, w)| format!("{cell:<w$}")).collect();
+        println!("{}", line.join("  ").trim_end());
     }
 }
 
-fn check_status(repo: &PathBuf) -> &'static str {
-    // Fetch remote without merging
-    let _ = Command::new("git")
-        .args(["-C", &repo.to_string_lossy(), "remote", "update"])
+/// Map `f` over items on a bounded pool of threads, keeping the input order
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..MAX_JOBS.min(items.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else { break };
+                let result = f(item);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+fn git_rev(repo: &Path, rev: &str) -> String {
+    git_output(repo, &["rev-parse", rev])
+}
+
+/// Run git, turning a failure into its trimmed stderr
+fn git_checked(args: &[&str]) -> Result<(), String> {
+    let out = Command::new("git")
+        .args(args)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).trim().lines().last().unwrap_or("git failed").to_string())
+    }
+}
 
-    let local = git_rev(repo, "HEAD");
-    let remote = git_rev(repo, "@{u}");
+/// Check whether a clone matches a `--plugin` filter: its key, a source it was resolved for
+/// (or a parent path of one), or a plugin name or path inside the repo like `colors`
+fn matches_plugin(lock: &Lockfile, key: &str, filter: &str) -> bool {
+    if key == filter {
+        return true;
+    }
+    let Some(entry) = lock.repos.get(key) else { return false };
+    let wanted = RemoteUrl::parse(filter);
+    entry.sources.iter().filter_map(|s| RemoteUrl::parse(s)).any(|source| match &wanted {
+        Some(wanted) => wanted.key() == source.key() && is_path_prefix(&wanted.path, &source.path),
+        None => {
+            let segments: Vec<&str> = source.path.split('/').filter(|s| !s.is_empty()).collect();
+            (1..=segments.len()).any(|n| segments[n - 1] == filter || segments[..n].join("/") == filter)
+        }
+    })
+}
 
-    if local.is_empty() || remote.is_empty() {
-        "unknown"
-    } else if local == remote {
-        "up to date"
-    } else {
-        "update available"
+/// Whether `prefix` is `path` or one of its parent directories
+fn is_path_prefix(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty() || path == prefix || path.starts_with(&format!("{prefix}/"))
+}
+
+fn git_lines(repo: &Path, args: &[&str]) -> Option<Vec<String>> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
     }
+    Some(String::from_utf8_lossy(&out.stdout).lines().map(String::from).collect())
 }
 
-fn git_rev(repo: &PathBuf, rev: &str) -> String {
+fn git_output(repo: &Path, args: &[&str]) -> String {
     Command::new("git")
-        .args(["-C", &repo.to_string_lossy(), "rev-parse", rev])
+        .arg("-C")
+        .arg(repo)
+        .args(args)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::null())
@@ -158,38 +817,7 @@ fn git_rev(repo: &PathBuf, rev: &str) -> String {
         .unwrap_or_default()
 }
 
-/// Update all remote plugins (git pull)
-pub fn update_plugins() {
-    let repos = find_repos();
-    if repos.is_empty() {
-        println!("no remote plugins to update");
-        return;
-    }
-    let base = plugins_base();
-    for repo in &repos {
-        let rel = repo.strip_prefix(&base).unwrap_or(repo);
-        print!("{} ... ", rel.display());
-        let status = Command::new("git")
-            .args(["-C", &repo.to_string_lossy(), "pull", "--ff-only"])
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
-        match status {
-            Ok(out) if out.status.success() => {
-                let msg = String::from_utf8_lossy(&out.stdout);
-                println!("{}", msg.trim());
-            }
-            Ok(out) => {
-                let err = String::from_utf8_lossy(&out.stderr);
-                println!("failed: {}", err.trim());
-            }
-            Err(e) => println!("failed: {e}"),
-        }
-    }
-}
-
-fn git_short_log(repo: &PathBuf) -> String {
+fn git_short_log(repo: &Path) -> String {
     Command::new("git")
         .args(["-C", &repo.to_string_lossy(), "log", "-1", "--format=%h %ar"])
         .stdin(Stdio::null())
@@ -201,7 +829,7 @@ fn git_short_log(repo: &PathBuf) -> String {
         .unwrap_or_default()
 }
 
-fn clone_repo(url: &GithubUrl, repo_dir: &PathBuf) {
+fn clone_repo(url: &RemoteUrl, repo_dir: &Path) {
     // Create parent directories
     if let Some(parent) = repo_dir.parent() {
         std::fs::create_dir_all(parent).unwrap_or_else(|e| {
@@ -210,17 +838,17 @@ fn clone_repo(url: &GithubUrl, repo_dir: &PathBuf) {
         });
     }
 
+    // `--branch` takes branches and tags; a commit is fetched by its full hash after cloning
+    let commit = is_commit(&url.git_ref);
+    let target = if commit { "--no-checkout" } else { "--branch" };
+    let mut args = vec!["clone", "--sparse", "--filter=blob:none", "--depth=1", target];
+    if !commit {
+        args.push(&url.git_ref);
+    }
+    let repo = repo_dir.to_string_lossy();
+    args.extend([url.clone_url.as_str(), &repo]);
     let status = Command::new("git")
-        .args([
-            "clone",
-            "--sparse",
-            "--filter=blob:none",
-            "--depth=1",
-            "--branch",
-            &url.git_ref,
-            &url.clone_url(),
-            &repo_dir.to_string_lossy(),
-        ])
+        .args(&args)
         .stdin(Stdio::null())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -230,6 +858,9 @@ fn clone_repo(url: &GithubUrl, repo_dir: &PathBuf) {
         Ok(s) if s.success() => {}
         Ok(s) => {
             eprintln!("git clone failed with exit code: {}", s.code().unwrap_or(-1));
+            if url.git_ref.len() >= 7 && url.git_ref.chars().all(|c| c.is_ascii_hexdigit()) {
+                eprintln!("pal: to pin a commit, use its full 40-character hash: @{}", url.git_ref);
+            }
             std::process::exit(1);
         }
         Err(e) => {
@@ -237,9 +868,24 @@ fn clone_repo(url: &GithubUrl, repo_dir: &PathBuf) {
             std::process::exit(1);
         }
     }
+
+    if commit {
+        let fetched = git_checked(&["-C", &repo, "fetch", "--quiet", "--depth=1", "origin", &url.git_ref])
+            .and_then(|()| git_checked(&["-C", &repo, "checkout", "--quiet", "--detach", &url.git_ref]));
+        if let Err(e) = fetched {
+            eprintln!("pal: could not check out commit {} of {}: {e}", url.git_ref, url.clone_url);
+            std::fs::remove_dir_all(repo_dir).ok();
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Whether a ref is a full commit hash rather than a branch or tag
+fn is_commit(git_ref: &str) -> bool {
+    git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit())
 }
 
-fn sparse_checkout_add(url: &GithubUrl, repo_dir: &PathBuf) {
+fn sparse_checkout_add(url: &RemoteUrl, repo_dir: &Path) {
     let status = Command::new("git")
         .args(["-C", &repo_dir.to_string_lossy(), "sparse-checkout", "add", &url.path])
         .stdin(Stdio::null())
@@ -0,0 +1,433 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::plugins;
+
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Palette,
+    Frontend,
+    Action,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Lang {
+    Bash,
+    Python,
+    Rust,
+}
+
+pub struct Options {
+    pub kind: Kind,
+    pub name: String,
+    pub lang: Lang,
+    /// Palette reads a query from the frontend (`input = true`)
+    pub input: bool,
+    /// Palette lists items from a bundled data.json
+    pub data: bool,
+    pub dir: Option<PathBuf>,
+    pub register: bool,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Palette => "palette",
+            Self::Frontend => "frontend",
+            Self::Action => "action",
+        }
+    }
+}
+
+/// Create a plugin directory from a template: plugin.toml, entrypoint and sample data
+pub fn new_plugin(opts: &Options) {
+    let kind = opts.kind.as_str();
+    // The name ends up in paths, plugin.toml, Cargo.toml and the user config
+    if opts.name.is_empty() || !opts.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        eprintln!("invalid plugin name '{}': use letters, digits, '_' and '-'", opts.name);
+        process::exit(1);
+    }
+    if opts.kind != Kind::Palette && (opts.input || opts.data) {
+        eprintln!("--input and --data only apply to palettes");
+        process::exit(1);
+    }
+    let dir = opts.dir.clone().unwrap_or_else(|| {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.config"))
+            .join(format!("pal/plugins/{kind}s/{}", opts.name))
+    });
+    if dir.read_dir().is_ok_and(|mut d| d.next().is_some()) {
+        eprintln!("{} already exists and is not empty", dir.display());
+        process::exit(1);
+    }
+
+    let mut files = vec![("plugin.toml", plugin_toml(opts), false)];
+    match opts.lang {
+        Lang::Bash => files.push(("run.sh", bash(opts), true)),
+        Lang::Python => files.push(("run.py", python(opts), true)),
+        Lang::Rust => {
+            files.push(("run.sh", RUST_WRAPPER.replace("{{name}}", &opts.name), true));
+            files.push(("Cargo.toml", RUST_MANIFEST.replace("{{name}}", &opts.name), false));
+            files.push(("src/main.rs", rust(opts), false));
+            files.push((".gitignore", "target/\n".to_string(), false));
+        }
+    }
+    if opts.data {
+        files.push(("data.json", SAMPLE_DATA.to_string(), false));
+    }
+
+    for (file, content, executable) in &files {
+        write_file(&dir.join(file), content, *executable);
+    }
+    println!("created {kind} '{}' in {}", opts.name, dir.display());
+    for (file, ..) in &files {
+        println!("  {file}");
+    }
+
+    let base = dir.to_string_lossy();
+    if opts.register {
+        if let Err(e) = plugins::register(kind, &opts.name, &base) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    } else if opts.kind == Kind::Action && opts.dir.is_none() {
+        // Actions in the plugins dir resolve by name
+        println!("\ntry it: echo hello | pal action {}", opts.name);
+    } else {
+        println!("\nadd it to your config (or rerun with --register):\n\n[{kind}.{}]\nbase = \"{base}\"", opts.name);
+    }
+}
+
+fn write_file(path: &Path, content: &str, executable: bool) {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            eprintln!("failed to create {}: {e}", parent.display());
+            process::exit(1);
+        }
+    }
+    if let Err(e) = std::fs::write(path, content) {
+        eprintln!("failed to write {}: {e}", path.display());
+        process::exit(1);
+    }
+    if executable {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).ok();
+    }
+}
+
+fn plugin_toml(opts: &Options) -> String {
+    let entrypoint = match opts.lang {
+        Lang::Python => "run.py",
+        Lang::Bash | Lang::Rust => "run.sh",
+    };
+    let mut toml = format!(
+        "name = \"{name}\"\ndesc = \"{name} {kind}\"\nversion = \"0.1\"\nkind = \"{kind}\"\n\ncontract_version = \"0.0.1\"\ncommand = [\"{entrypoint}\"]\n",
+        name = opts.name,
+        kind = opts.kind.as_str(),
+    );
+    match opts.kind {
+        Kind::Palette if opts.input => {
+            toml.push_str(&format!("input = true\ninput_prompt = \"{}\"\n", opts.name));
+        }
+        // The template frontends ask on the terminal
+        Kind::Frontend => toml.push_str("capabilities = [\"pick\"]\ntty = true\n"),
+        Kind::Palette | Kind::Action => {}
+    }
+    // The bash templates parse items with jq
+    if matches!(opts.lang, Lang::Bash) && opts.kind != Kind::Action {
+        toml.push_str("requirements = [\"jq\"]\n");
+    }
+    toml
+}
+
+const SAMPLE_DATA: &str = r#"{"id":"hello","name":"Hello","icon":"face-smile"}
+{"id":"world","name":"World","icon":"applications-internet"}
+"#;
+
+fn bash(opts: &Options) -> String {
+    let body = match opts.kind {
+        Kind::Palette => {
+            let list = match (opts.input, opts.data) {
+                (false, false) => concat!(
+                    "  echo '{\"id\":\"hello\",\"name\":\"Hello\",\"icon\":\"face-smile\"}'\n",
+                    "  echo '{\"id\":\"world\",\"name\":\"World\",\"icon\":\"applications-internet\"}'\n",
+                ),
+                (false, true) => "  cat \"$DATA\"\n",
+                (true, false) => concat!(
+                    "  # The query typed in the frontend arrives on stdin\n",
+                    "  query=$(cat)\n",
+                    "  [[ -z \"$query\" ]] && return\n",
+                    "  jq -cn --arg q \"$query\" '{id: $q, name: \"Search: \\($q)\"}'\n",
+                ),
+                (true, true) => concat!(
+                    "  # The query typed in the frontend arrives on stdin\n",
+                    "  query=$(cat)\n",
+                    "  grep -iF -- \"$query\" \"$DATA\"\n",
+                ),
+            };
+            format!(
+                "{data}list() {{\n{list}}}\n\npick() {{\n  item=$(cat)\n  # `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects like opening or killing things\n  [[ \"$_PAL_DRY_RUN\" == 1 ]] && return\n  # Output is passed to the item's `then` actions\n  echo \"$item\" | jq -r '.id'\n}}\n\nCMD=$1; shift\ncase \"$CMD\" in\n  list) list ;;\n  pick) pick ;;\n  *) echo \"Unknown command: $CMD\" >&2 ;;\nesac\n",
+                data = if opts.data { "DATA=\"$(dirname \"$0\")/data.json\"\n\n" } else { "" },
+            )
+        }
+        Kind::Frontend => concat!(
+            "# Items arrive as JSON lines on stdin; print the selected item\n",
+            "run() {\n",
+            "  items=$(cat)\n",
+            "  mapfile -t names < <(echo \"$items\" | jq -r '.name')\n",
+            "  PS3=\"pal> \"\n",
+            "  select name in \"${names[@]}\"; do break; done </dev/tty\n",
+            "  [[ -n \"$name\" ]] && echo \"$items\" | jq -c --arg n \"$name\" 'select(.name == $n)' | head -1\n",
+            "}\n",
+            "\n",
+            "# Message arrives on stdin; print what the user typed\n",
+            "prompt() {\n",
+            "  message=$(cat)\n",
+            "  read -rp \"$message> \" answer </dev/tty\n",
+            "  echo \"$answer\"\n",
+            "}\n",
+            "\n",
+            "# Query for input palettes; pal lists and picks with the answer\n",
+            "input_run() {\n",
+            "  prompt\n",
+            "}\n",
+            "\n",
+            "CMD=$1; shift\n",
+            "case \"$CMD\" in\n",
+            "  run) run ;;\n",
+            "  prompt) prompt ;;\n",
+            "  input_run) input_run ;;\n",
+            "  *) echo \"Unknown command: $CMD\" >&2 ;;\n",
+            "esac\n",
+        )
+        .to_string(),
+        Kind::Action => concat!(
+            "# Value arrives on stdin; output is passed to the next action in a chain\n",
+            "run() {\n",
+            "  value=$(cat)\n",
+            "  # `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects\n",
+            "  [[ \"$_PAL_DRY_RUN\" == 1 ]] && return\n",
+            "  echo \"$value\"\n",
+            "}\n",
+            "\n",
+            "CMD=$1; shift\n",
+            "case \"$CMD\" in\n",
+            "  run) run ;;\n",
+            "  *) echo \"Unknown command: $CMD\" >&2 ;;\n",
+            "esac\n",
+        )
+        .to_string(),
+    };
+    format!(
+        "#!/usr/bin/env bash\n\n# {name} {kind} for pal\n# Plugin config is available as JSON in $_PAL_PLUGIN_CONFIG\n\n{body}",
+        name = opts.name,
+        kind = opts.kind.as_str(),
+    )
+}
+
+fn python(opts: &Options) -> String {
+    let body = match opts.kind {
+        Kind::Palette => {
+            let list = match (opts.input, opts.data) {
+                (false, false) => concat!(
+                    "    for item in [\n",
+                    "        {\"id\": \"hello\", \"name\": \"Hello\", \"icon\": \"face-smile\"},\n",
+                    "        {\"id\": \"world\", \"name\": \"World\", \"icon\": \"applications-internet\"},\n",
+                    "    ]:\n",
+                    "        print(json.dumps(item))\n",
+                ),
+                (false, true) => "    print(DATA.read_text(), end=\"\")\n",
+                (true, false) => concat!(
+                    "    # The query typed in the frontend arrives on stdin\n",
+                    "    query = sys.stdin.read().strip()\n",
+                    "    if query:\n",
+                    "        print(json.dumps({\"id\": query, \"name\": f\"Search: {query}\"}))\n",
+                ),
+                (true, true) => concat!(
+                    "    # The query typed in the frontend arrives on stdin\n",
+                    "    query = sys.stdin.read().strip().lower()\n",
+                    "    for line in DATA.read_text().splitlines():\n",
+                    "        if query in line.lower():\n",
+                    "            print(line)\n",
+                ),
+            };
+            format!(
+                "{data}def list_items():\n{list}\n\ndef pick():\n    item = json.loads(sys.stdin.read())\n    # `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects like opening or killing things\n    if os.environ.get(\"_PAL_DRY_RUN\") == \"1\":\n        return\n    # Output is passed to the item's `then` actions\n    print(item.get(\"id\", \"\"))\n\n\nCOMMANDS = {{\"list\": list_items, \"pick\": pick}}\n",
+                data = if opts.data { "DATA = Path(__file__).parent / \"data.json\"\n\n\n" } else { "" },
+            )
+        }
+        Kind::Frontend => concat!(
+            "def ask(message):\n",
+            "    with open(\"/dev/tty\") as tty_in, open(\"/dev/tty\", \"w\") as tty_out:\n",
+            "        tty_out.write(message)\n",
+            "        tty_out.flush()\n",
+            "        return tty_in.readline().strip()\n",
+            "\n",
+            "\n",
+            "def run():\n",
+            "    \"\"\"Items arrive as JSON lines on stdin; print the selected item\"\"\"\n",
+            "    items = [line for line in sys.stdin.read().splitlines() if line]\n",
+            "    for i, line in enumerate(items, 1):\n",
+            "        print(f\"{i}) {json.loads(line).get('name', '')}\", file=sys.stderr)\n",
+            "    choice = ask(\"pal> \")\n",
+            "    if choice.isdigit() and 0 < int(choice) <= len(items):\n",
+            "        print(items[int(choice) - 1])\n",
+            "\n",
+            "\n",
+            "def prompt():\n",
+            "    \"\"\"Message arrives on stdin; print what the user typed\"\"\"\n",
+            "    print(ask(f\"{sys.stdin.read().strip()}> \"))\n",
+            "\n",
+            "\n",
+            "def input_run():\n",
+            "    \"\"\"Query for input palettes; pal lists and picks with the answer\"\"\"\n",
+            "    prompt()\n",
+            "\n",
+            "\n",
+            "COMMANDS = {\"run\": run, \"prompt\": prompt, \"input_run\": input_run}\n",
+        )
+        .to_string(),
+        Kind::Action => concat!(
+            "def run():\n",
+            "    \"\"\"Value arrives on stdin; output is passed to the next action in a chain\"\"\"\n",
+            "    value = sys.stdin.read()\n",
+            "    # `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects\n",
+            "    if os.environ.get(\"_PAL_DRY_RUN\") == \"1\":\n",
+            "        return\n",
+            "    print(value, end=\"\")\n",
+            "\n",
+            "\n",
+            "COMMANDS = {\"run\": run}\n",
+        )
+        .to_string(),
+    };
+    format!(
+        "#!/usr/bin/env python3\n\"\"\"{name} {kind} for pal\"\"\"\n\nimport json\nimport os\nimport sys\n{path}\n# Plugin config from pal\nCONFIG = json.loads(os.environ.get(\"_PAL_PLUGIN_CONFIG\") or \"{{}}\")\n\n{body}\n\nif __name__ == \"__main__\":\n    cmd = sys.argv[1] if len(sys.argv) > 1 else \"\"\n    if cmd in COMMANDS:\n        COMMANDS[cmd]()\n    else:\n        print(f\"Unknown command: {{cmd}}\", file=sys.stderr)\n",
+        name = opts.name,
+        kind = opts.kind.as_str(),
+        path = if opts.data { "from pathlib import Path\n" } else { "" },
+    )
+}
+
+const RUST_WRAPPER: &str = r#"#!/usr/bin/env bash
+
+# Builds the {{name}} plugin on first use, then runs it
+dir="$(cd "$(dirname "$0")" && pwd)"
+bin="$dir/target/release/{{name}}"
+if [[ ! -x "$bin" ]]; then
+  cargo build --quiet --release --manifest-path "$dir/Cargo.toml" >&2 || exit 1
+fi
+exec "$bin" "$@"
+"#;
+
+const RUST_MANIFEST: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde_json = "1"
+"#;
+
+fn rust(opts: &Options) -> String {
+    let (functions, commands) = match opts.kind {
+        Kind::Palette => {
+            let list = match (opts.input, opts.data) {
+                (false, false) => concat!(
+                    "    println!(\"{}\", json!({\"id\": \"hello\", \"name\": \"Hello\", \"icon\": \"face-smile\"}));\n",
+                    "    println!(\"{}\", json!({\"id\": \"world\", \"name\": \"World\", \"icon\": \"applications-internet\"}));\n",
+                ),
+                (false, true) => "    print!(\"{}\", std::fs::read_to_string(DATA).unwrap_or_default());\n",
+                (true, false) => concat!(
+                    "    // The query typed in the frontend arrives on stdin\n",
+                    "    let query = stdin();\n",
+                    "    let query = query.trim();\n",
+                    "    if !query.is_empty() {\n",
+                    "        println!(\"{}\", json!({\"id\": query, \"name\": format!(\"Search: {query}\")}));\n",
+                    "    }\n",
+                ),
+                (true, true) => concat!(
+                    "    // The query typed in the frontend arrives on stdin\n",
+                    "    let query = stdin().trim().to_lowercase();\n",
+                    "    let data = std::fs::read_to_string(DATA).unwrap_or_default();\n",
+                    "    for line in data.lines().filter(|l| l.to_lowercase().contains(&query)) {\n",
+                    "        println!(\"{line}\");\n",
+                    "    }\n",
+                ),
+            };
+            let data = if opts.data { "const DATA: &str = concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/data.json\");\n\n" } else { "" };
+            (
+                format!(
+                    "{data}fn list() {{\n{list}}}\n\nfn pick() {{\n    let item: Value = serde_json::from_str(&stdin()).unwrap_or_default();\n    // `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects like opening or killing things\n    if std::env::var(\"_PAL_DRY_RUN\").as_deref() == Ok(\"1\") {{\n        return;\n    }}\n    // Output is passed to the item's `then` actions\n    println!(\"{{}}\", item[\"id\"].as_str().unwrap_or_default());\n}}\n"
+                ),
+                "        Some(\"list\") => list(),\n        Some(\"pick\") => pick(),\n",
+            )
+        }
+        Kind::Frontend => (
+            concat!(
+                "fn ask(message: &str) -> String {\n",
+                "    let mut tty = std::fs::OpenOptions::new().read(true).write(true).open(\"/dev/tty\").expect(\"no terminal\");\n",
+                "    write!(tty, \"{message}\").ok();\n",
+                "    let mut answer = String::new();\n",
+                "    std::io::BufReader::new(tty).read_line(&mut answer).ok();\n",
+                "    answer.trim().to_string()\n",
+                "}\n",
+                "\n",
+                "/// Items arrive as JSON lines on stdin; print the selected item\n",
+                "fn run() {\n",
+                "    let input = stdin();\n",
+                "    let items: Vec<&str> = input.lines().filter(|l| !l.is_empty()).collect();\n",
+                "    for (i, line) in items.iter().enumerate() {\n",
+                "        let item: Value = serde_json::from_str(line).unwrap_or_default();\n",
+                "        eprintln!(\"{}) {}\", i + 1, item[\"name\"].as_str().unwrap_or_default());\n",
+                "    }\n",
+                "    let choice: usize = ask(\"pal> \").parse().unwrap_or(0);\n",
+                "    if let Some(item) = choice.checked_sub(1).and_then(|i| items.get(i)) {\n",
+                "        println!(\"{item}\");\n",
+                "    }\n",
+                "}\n",
+                "\n",
+                "/// Message arrives on stdin; print what the user typed\n",
+                "fn prompt() {\n",
+                "    println!(\"{}\", ask(&format!(\"{}> \", stdin().trim())));\n",
+                "}\n",
+            )
+            .to_string(),
+            concat!(
+                "        Some(\"run\") => run(),\n",
+                "        // Query for input palettes; pal lists and picks with the answer\n",
+                "        Some(\"prompt\" | \"input_run\") => prompt(),\n",
+            ),
+        ),
+        Kind::Action => (
+            concat!(
+                "/// Value arrives on stdin; output is passed to the next action in a chain\n",
+                "fn run() {\n",
+                "    let value = stdin();\n",
+                "    // `pal plugin test` sets _PAL_DRY_RUN=1: skip side effects\n",
+                "    if std::env::var(\"_PAL_DRY_RUN\").as_deref() == Ok(\"1\") {\n",
+                "        return;\n",
+                "    }\n",
+                "    print!(\"{value}\");\n",
+                "}\n",
+            )
+            .to_string(),
+            "        Some(\"run\") => run(),\n",
+        ),
+    };
+    let imports = match opts.kind {
+        Kind::Palette if opts.data => "use std::io::Read;\n\nuse serde_json::Value;\n",
+        Kind::Palette => "use std::io::Read;\n\nuse serde_json::{json, Value};\n",
+        Kind::Frontend => "use std::io::{BufRead, Read, Write};\n\nuse serde_json::Value;\n",
+        Kind::Action => "use std::io::Read;\n",
+    };
+    format!(
+        "//! {name} {kind} for pal. Plugin config is available as JSON in $_PAL_PLUGIN_CONFIG\n\n{imports}\nfn stdin() -> String {{\n    let mut input = String::new();\n    std::io::stdin().read_to_string(&mut input).ok();\n    input\n}}\n\n{functions}\nfn main() {{\n    match std::env::args().nth(1).as_deref() {{\n{commands}        cmd => eprintln!(\"Unknown command: {{}}\", cmd.unwrap_or_default()),\n    }}\n}}\n",
+        name = opts.name,
+        kind = opts.kind.as_str(),
+    )
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{ActionChain, Config};
+use crate::scaffold::Kind;
+
+/// An item printed by a palette's `list`, one JSON object per line
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Item {
+    /// Unique id, defaults to `name`
+    pub id: Option<String>,
+    /// Text shown in the frontend
+    pub name: String,
+    /// Secondary text
+    pub desc: Option<String>,
+    /// Icon: freedesktop name, image path or hex color
+    pub icon: Option<String>,
+    /// Freedesktop icon name for GUI frontends
+    pub icon_xdg: Option<String>,
+    /// Single glyph (e.g. a Nerd Font icon) for text frontends
+    pub icon_utf: Option<String>,
+    /// Extra search terms
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Values asked for before the item is picked, exported as `PAL_<KEY>`
+    #[serde(default)]
+    #[schemars(schema_with = "prompts_schema")]
+    pub prompts: Vec<Prompt>,
+    /// Actions run after the pick, fed the picked value
+    pub then: Option<ActionChain>,
+    /// Item needs a terminal
+    #[serde(default)]
+    pub terminal: bool,
+    /// Any other field is exported as `PAL_<KEY>` on pick
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Prompt {
+    /// Name the value is stored under, used in `{{key}}` templates and `PAL_<KEY>`
+    pub key: String,
+    /// Question shown to the user, defaults to `key`
+    pub message: Option<String>,
+    #[serde(rename = "type", default)]
+    pub kind: PromptKind,
+    /// Choices for `choice` prompts
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Used when the answer is empty or the prompt is skipped
+    pub default: Option<String>,
+    /// Condition on earlier answers, e.g. `env == "prod" && !force`
+    pub when: Option<String>,
+}
+
+/// `Vec<Prompt>`, with the rule serde can't express: a choice prompt needs `options`
+fn prompts_schema(gen: &mut SchemaGenerator) -> Schema {
+    let schema = gen.subschema_for::<Vec<Prompt>>();
+    if let Some(Schema::Object(prompt)) = gen.definitions_mut().get_mut("Prompt") {
+        let choice = json!({"properties": {"type": {"const": "choice"}}, "required": ["type"]});
+        let options = json!({"required": ["options"], "properties": {"options": {"minItems": 1}}});
+        let subschemas = prompt.subschemas();
+        subschemas.if_schema = serde_json::from_value(choice).ok();
+        subschemas.then_schema = serde_json::from_value(options).ok();
+    }
+    schema
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptKind {
+    #[default]
+    Text,
+    Choice,
+}
+
+/// `plugin.toml` of a palette, frontend or action plugin
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PluginManifest {
+    pub name: Option<String>,
+    pub desc: Option<String>,
+    pub version: Option<String>,
+    /// palette, frontend or action; tools like `pal plugin test` guess it from the directory otherwise
+    pub kind: Option<Kind>,
+    /// Plugin contract the plugin implements, e.g. "0.0.1"
+    pub contract_version: Option<String>,
+    /// Entrypoint relative to the plugin dir; called with the command name (list, pick, run, ...)
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Frontend capabilities, e.g. ["pick"]
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Commands the plugin needs on PATH
+    #[serde(default)]
+    pub requirements: Vec<String>,
+    pub icon: Option<String>,
+    pub icon_xdg: Option<String>,
+    pub icon_utf: Option<String>,
+    /// Palette takes a query from the frontend
+    #[serde(default)]
+    pub input: bool,
+    pub input_prompt: Option<String>,
+    /// Re-list on every keystroke
+    #[serde(default)]
+    pub live: bool,
+    /// List items from the palette's `data` file
+    #[serde(default)]
+    pub auto_list: bool,
+    /// Run `default_action` on the picked item's `action_key` value
+    #[serde(default)]
+    pub auto_pick: bool,
+    pub default_action: Option<ActionChain>,
+    pub action_key: Option<String>,
+    /// Items need a terminal
+    #[serde(default)]
+    pub terminal: bool,
+    /// Frontend runs in the invoking terminal
+    #[serde(default)]
+    pub tty: bool,
+    /// Plugin-specific settings, passed in `_PAL_PLUGIN_CONFIG`
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Target {
+    Item,
+    Plugin,
+    Config,
+}
+
+/// Print the JSON Schema of an item, plugin.toml or the config
+pub fn print(target: Target) {
+    println!("{}", generate(target));
+}
+
+/// The JSON Schema of a target as pretty-printed JSON, as shipped in `schemas/`
+fn generate(target: Target) -> String {
+    let schema = match target {
+        Target::Item => schemars::schema_for!(Item),
+        Target::Plugin => schemars::schema_for!(PluginManifest),
+        Target::Config => schemars::schema_for!(Config),
+    };
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
+}
+
+/// Whether the config schema has a place for a dotted key. Tables with fixed fields like
+/// `general` reject unknown keys; free-form ones like a palette's plugin settings accept anything.
+pub fn known_config_key(key: &str) -> bool {
+    let root = serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default();
+    let mut node = &root;
+    for part in key.split('.') {
+        node = resolve_ref(&root, node);
+        if let Some(field) = node.get("properties").and_then(|p| p.get(part)) {
+            node = field;
+            continue;
+        }
+        match node.get("additionalProperties") {
+            Some(Value::Bool(allowed)) => return *allowed,
+            Some(schema) => node = schema,
+            None => return node.get("properties").is_none(),
+        }
+    }
+    true
+}
+
+/// Follow a `$ref`, also when wrapped in a single-entry `allOf` as schemars does for fields with defaults
+fn resolve_ref<'a>(root: &'a Value, node: &'a Value) -> &'a Value {
+    let reference = node
+        .get("$ref")
+        .or_else(|| node.get("allOf")?.get(0)?.get("$ref"))
+        .and_then(|r| r.as_str())
+        .and_then(|r| r.strip_prefix("#/definitions/"));
+    match reference.and_then(|name| root.get("definitions")?.get(name)) {
+        Some(definition) => definition,
+        None => node,
+    }
+}
+
+/// Check an item against the item schema. Returns (errors, warnings).
+pub fn validate_item(item: &Value) -> (Vec<String>, Vec<String>) {
+    let mut errors = vec![];
+    let mut warnings = vec![];
+    let Some(obj) = item.as_object() else {
+        return (vec!["item is not a JSON object".into()], warnings);
+    };
+
+    match obj.get("name") {
+        Some(Value::String(_)) => {}
+        Some(_) => errors.push("'name' must be a string".into()),
+        None => errors.push("missing 'name'".into()),
+    }
+    // Optional fields may also be null, as in the schema
+    for field in ["id", "icon", "icon_xdg", "icon_utf"] {
+        if obj.get(field).is_some_and(|v| !v.is_string() && !v.is_null()) {
+            errors.push(format!("'{field}' must be a string"));
+        }
+    }
+    if let Some(icon) = obj.get("icon_utf").and_then(|v| v.as_str()) {
+        if icon.chars().count() > 2 {
+            warnings.push(format!("'icon_utf' should be a single glyph, got '{icon}'"));
+        }
+    }
+    if let Some(icon) = obj.get("icon_xdg").and_then(|v| v.as_str()) {
+        if icon.contains(char::is_whitespace) {
+            warnings.push(format!("'icon_xdg' should be an icon name, got '{icon}'"));
+        }
+    }
+    if let Some(keywords) = obj.get("keywords") {
+        if !keywords.as_array().is_some_and(|a| a.iter().all(|k| k.is_string())) {
+            errors.push("'keywords' must be an array of strings".into());
+        }
+    }
+    if obj.get("terminal").is_some_and(|v| !v.is_boolean()) {
+        errors.push("'terminal' must be a boolean".into());
+    }
+    if let Some(then) = obj.get("then").filter(|v| !v.is_null()) {
+        if let Err(e) = serde_json::from_value::<ActionChain>(then.clone()) {
+            errors.push(format!("'then' is not an action or list of actions: {e}"));
+        }
+    }
+    if let Some(prompts) = obj.get("prompts") {
+        match prompts.as_array() {
+            Some(prompts) => {
+                for (i, prompt) in prompts.iter().enumerate() {
+                    errors.extend(validate_prompt(prompt).into_iter().map(|e| format!("prompts[{i}]: {e}")));
+                }
+            }
+            None => errors.push("'prompts' must be an array".into()),
+        }
+    }
+    // Anything the checks above don't cover, straight from the schema types
+    if errors.is_empty() {
+        if let Err(e) = serde_json::from_value::<Item>(item.clone()) {
+            errors.push(e.to_string());
+        }
+    }
+    (errors, warnings)
+}
+
+fn validate_prompt(prompt: &Value) -> Vec<String> {
+    let Some(obj) = prompt.as_object() else { return vec!["prompt is not an object".into()] };
+    let mut errors = vec![];
+    if !obj.get("key").is_some_and(|v| v.is_string()) {
+        errors.push("missing 'key'".into());
+    }
+    for field in ["message", "default", "when"] {
+        if obj.get(field).is_some_and(|v| !v.is_string() && !v.is_null()) {
+            errors.push(format!("'{field}' must be a string"));
+        }
+    }
+    match obj.get("type").map(|t| t.as_str()) {
+        None | Some(Some("text")) => {}
+        Some(Some("choice")) => {
+            let options = obj.get("options").and_then(|v| v.as_array());
+            if !options.is_some_and(|o| !o.is_empty() && o.iter().all(|v| v.is_string())) {
+                errors.push("choice prompt needs 'options', a non-empty array of strings".into());
+            }
+        }
+        Some(t) => errors.push(format!("unknown type {}", t.map_or("(not a string)".to_string(), |t| format!("'{t}'")))),
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_files_match_the_types() {
+        let files = [
+            (Target::Item, include_str!("../schemas/item.schema.json")),
+            (Target::Plugin, include_str!("../schemas/plugin.schema.json")),
+            (Target::Config, include_str!("../schemas/config.schema.json")),
+        ];
+        for (target, file) in files {
+            assert_eq!(file.trim_end(), generate(target), "schemas/ is stale, regenerate it with `pal schema`");
+        }
+    }
+
+    #[test]
+    fn validate_item_agrees_with_the_schema() {
+        let schema: Value = serde_json::from_str(&generate(Target::Item)).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let items = [
+            json!({"name": "a"}),
+            json!({"name": "a", "id": "x", "desc": "d", "icon": "i", "icon_xdg": "i", "icon_utf": "i", "keywords": ["k"]}),
+            json!({"name": "a", "id": null, "desc": null, "icon": null, "then": null}),
+            json!({"name": "a", "terminal": true, "then": "copy", "custom": {"any": [1]}}),
+            json!({"name": "a", "then": ["copy", {"name": "cmd", "args": {"x": 1}}]}),
+            json!({"name": "a", "prompts": [{"key": "k"}, {"key": "env", "type": "choice", "options": ["a"], "when": "x", "default": "a", "message": "m"}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "message": null, "default": null, "when": null}]}),
+            json!({}),
+            json!([]),
+            json!("a"),
+            json!({"name": 1}),
+            json!({"name": null}),
+            json!({"name": "a", "id": 1}),
+            json!({"name": "a", "desc": false}),
+            json!({"name": "a", "keywords": "k"}),
+            json!({"name": "a", "keywords": [1]}),
+            json!({"name": "a", "keywords": null}),
+            json!({"name": "a", "terminal": "yes"}),
+            json!({"name": "a", "terminal": null}),
+            json!({"name": "a", "then": 1}),
+            json!({"name": "a", "prompts": {}}),
+            json!({"name": "a", "prompts": null}),
+            json!({"name": "a", "prompts": ["k"]}),
+            json!({"name": "a", "prompts": [{}]}),
+            json!({"name": "a", "prompts": [{"key": 1}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "type": "number"}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "type": "choice"}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "type": "choice", "options": []}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "type": "choice", "options": [1]}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "options": "a"}]}),
+            json!({"name": "a", "prompts": [{"key": "k", "message": 1}]}),
+        ];
+        let disagreements: Vec<_> = items
+            .iter()
+            .filter(|item| validate_item(item).0.is_empty() != validator.is_valid(item))
+            .map(|item| format!("{item}: {:?}", validate_item(item).0))
+            .collect();
+        assert!(disagreements.is_empty(), "{disagreements:#?}");
+    }
+}
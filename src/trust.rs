@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Allowlist of remote repos whose code pal may run, in `~/.config/pal/trust.toml`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    pub repos: BTreeMap<String, TrustedRepo>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TrustedRepo {
+    /// Last reviewed commit per ref
+    #[serde(default)]
+    pub refs: BTreeMap<String, String>,
+}
+
+impl TrustStore {
+    pub fn path() -> PathBuf {
+        dirs::config_dir()
+            .map(|p| p.join("pal/trust.toml"))
+            .unwrap_or_else(|| PathBuf::from("trust.toml"))
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|s| toml::from_str(&s).map_err(|e| eprintln!("trust.toml: {e}")).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        match toml::to_string_pretty(self) {
+            Ok(s) => {
+                if let Err(e) = std::fs::write(&path, s) {
+                    eprintln!("failed to write {}: {e}", path.display());
+                }
+            }
+            Err(e) => eprintln!("failed to serialize trust store: {e}"),
+        }
+    }
+}
+
+type Prompter = Box<dyn Fn(&str, &[String]) -> bool + Send>;
+
+static PROMPTER: Mutex<Option<Prompter>> = Mutex::new(None);
+
+/// Install the confirmation prompt used for trust decisions (usually the active frontend)
+pub fn set_prompter(prompter: Prompter) {
+    *PROMPTER.lock().unwrap() = Some(prompter);
+}
+
+/// Ask the user to confirm, showing details such as a file list or diff.
+/// Uses the installed prompter, falls back to the terminal, and refuses when non-interactive.
+pub fn confirm(message: &str, details: &[String]) -> bool {
+    if let Some(prompter) = PROMPTER.lock().unwrap().as_ref() {
+        return prompter(message, details);
+    }
+
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return false;
+    }
+    let mut stderr = std::io::stderr();
+    for line in details {
+        let _ = writeln!(stderr, "  {line}");
+    }
+    let _ = write!(stderr, "{message} [y/N] ");
+    let _ = stderr.flush();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
@@ -4,39 +4,105 @@ use std::process::{self, Command, Stdio};
 
 use serde::Serialize;
 
+use crate::context::Context;
+
 pub fn expand_path(path: &str) -> PathBuf {
-    // Handle github: remote plugins
-    if path.starts_with("github:") {
-        if let Some(local_path) = crate::remote::ensure_github(path) {
+    // Handle remote plugins (github:, gitlab:, git+...)
+    if crate::remote::is_remote(path) {
+        if let Some(local_path) = crate::remote::ensure(path) {
             return local_path;
         }
     }
 
-    if path.starts_with("~/") {
+    if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]);
+            return home.join(rest);
         }
     }
-    if path.starts_with('/') {
-        return PathBuf::from(path);
+    // Relative paths from the config were made absolute when it loaded
+    PathBuf::from(path)
+}
+
+/// Expand `*` and `?` wildcards in each component of a path, sorted lexically.
+/// Hidden entries only match patterns that start with a dot.
+pub fn glob(pattern: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !part.contains(['*', '?']) {
+            paths.iter_mut().for_each(|p| p.push(component));
+            continue;
+        }
+        paths = paths
+            .iter()
+            .flat_map(|dir| {
+                let mut matches: Vec<PathBuf> = std::fs::read_dir(dir)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        (part.starts_with('.') || !name.starts_with('.')) && wildcard_match(&part, &name)
+                    })
+                    .map(|entry| entry.path())
+                    .collect();
+                matches.sort();
+                matches
+            })
+            .collect();
     }
-    // Relative path - resolve from config directory
-    if let Ok(config_dir) = std::env::var("_PAL_CONFIG_DIR") {
-        let base = if config_dir.starts_with("~/") {
-            dirs::home_dir().map(|h| h.join(&config_dir[2..])).unwrap_or_else(|| PathBuf::from(&config_dir))
-        } else if config_dir.starts_with('/') {
-            PathBuf::from(&config_dir)
-        } else {
-            // config_dir is relative to cwd
-            std::env::current_dir().unwrap_or_default().join(&config_dir)
-        };
-        return base.join(path);
+    paths.retain(|p| p.exists());
+    paths
+}
+
+/// Match a name against `*` and `?` wildcards, case-sensitively
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // Classic two-pointer match, backtracking to the last `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
     }
-    PathBuf::from(path)
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
-pub fn run_command(exec: &Path, args: &[&str], stdin_data: Option<&str>) -> String {
-    let mut child = Command::new(exec)
+/// Run a command in a plugin context and return its stdout
+pub fn run_command(exec: &Path, args: &[&str], stdin_data: Option<&str>, ctx: &Context) -> String {
+    let output = command_output(exec, args, stdin_data, ctx);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Like `run_command`, but a non-zero exit status is returned as an error
+pub fn run_command_checked(exec: &Path, args: &[&str], stdin_data: Option<&str>, ctx: &Context) -> Result<String, String> {
+    let output = command_output(exec, args, stdin_data, ctx);
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!("{} exited with {}", exec.display(), output.status.code().unwrap_or(-1)))
+    }
+}
+
+fn command_output(exec: &Path, args: &[&str], stdin_data: Option<&str>, ctx: &Context) -> process::Output {
+    let mut child = ctx
+        .apply(&mut Command::new(exec))
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -53,12 +119,10 @@ pub fn run_command(exec: &Path, args: &[&str], stdin_data: Option<&str>) -> Stri
         }
     }
 
-    let output = child.wait_with_output().unwrap_or_else(|e| {
+    child.wait_with_output().unwrap_or_else(|e| {
         eprintln!("failed to wait on {}: {e}", exec.display());
         process::exit(1);
-    });
-
-    String::from_utf8_lossy(&output.stdout).into_owned()
+    })
 }
 
 pub fn merge_configs(plugin_toml: &toml::Value, user_config: &impl Serialize) -> toml::Value {